@@ -1,35 +1,197 @@
-use crate::read_lines;
+use crate::config::{AuthConfig, OAuth2Grant};
+use anyhow::anyhow;
 use base64::{engine::general_purpose, Engine as _};
+use serde::Deserialize;
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Access tokens are renewed when they are this close to expiry
+static REFRESH_MARGIN: Duration = Duration::from_secs(60);
 
 // ---------------------------------------------------------------------------------------------------------------------
-// Fetch userid and password from .env file
+/// The response from an OAuth 2.0 token endpoint
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+    refresh_token: Option<String>,
+}
+
+/// A cached access token
+#[derive(Debug)]
+struct AccessToken {
+    value: String,
+    expires_at: Option<Instant>,
+    refresh_token: Option<String>,
+}
+
+impl AccessToken {
+    fn from_response(response: TokenResponse) -> AccessToken {
+        AccessToken {
+            value: response.access_token,
+            expires_at: response
+                .expires_in
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+            refresh_token: response.refresh_token,
+        }
+    }
+
+    /// A token without an expiry time is assumed to remain valid
+    fn is_fresh(&self) -> bool {
+        match self.expires_at {
+            Some(expires_at) => Instant::now() + REFRESH_MARGIN < expires_at,
+            None => true,
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------------------------------------------------
-pub fn fetch_auth() -> Result<String, String> {
-    let mut user = String::from("unknown");
-    let mut pwd = String::from("unknown");
-
-    // Try to obtain userid and password from environment variable file .env
-    if let Ok(lines) = read_lines(".env") {
-        for line in lines {
-            match line {
-                Ok(l) => {
-                    if l.starts_with("SAP_USER") {
-                        let (_, u) = l.split_at(l.find("=").unwrap() + 1);
-                        user = u.to_owned();
-                    }
-                    if l.starts_with("SAP_PASSWORD") {
-                        let (_, p) = l.split_at(l.find("=").unwrap() + 1);
-                        pwd = p.to_owned();
-                    }
+/// Supplies the `Authorization` header value for requests to one SAP system
+///
+/// OAuth 2.0 access tokens are cached and renewed shortly before they expire.  If the token endpoint issued a refresh
+/// token, this is tried first; otherwise, a new token is requested using the configured grant.
+#[derive(Debug)]
+pub struct Authenticator {
+    auth: AuthConfig,
+    http_client: reqwest::Client,
+    token: Mutex<Option<AccessToken>>,
+}
+
+impl Authenticator {
+    /// Token requests are sent using the given client, so they present the same certificates as catalog requests
+    pub fn new(auth: &AuthConfig, http_client: reqwest::Client) -> Authenticator {
+        Authenticator {
+            auth: auth.clone(),
+            http_client,
+            token: Mutex::new(None),
+        }
+    }
+
+    /// The `Authorization` header value, or `None` if the client certificate alone identifies the user
+    pub async fn authorization(&self) -> Result<Option<String>, anyhow::Error> {
+        match &self.auth {
+            AuthConfig::Basic { user, password } => Ok(Some(format!(
+                "Basic {}",
+                general_purpose::STANDARD.encode(format!("{}:{}", user, password))
+            ))),
+            AuthConfig::OAuth2 { .. } => Ok(Some(format!("Bearer {}", self.access_token().await?))),
+            AuthConfig::ClientCertificate(_) => Ok(None),
+        }
+    }
+
+    // - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+    async fn access_token(&self) -> Result<String, anyhow::Error> {
+        // The lock must not be held across an await
+        let refresh_token = {
+            let token = self.token.lock().unwrap();
+
+            match token.as_ref() {
+                Some(token) if token.is_fresh() => return Ok(token.value.clone()),
+                Some(token) => token.refresh_token.clone(),
+                None => None,
+            }
+        };
+
+        let new_token = match refresh_token {
+            Some(refresh_token) => match self
+                .request_token(&[
+                    ("grant_type", "refresh_token"),
+                    ("refresh_token", &refresh_token),
+                ])
+                .await
+            {
+                Ok(token) => token,
+                Err(err) => {
+                    log::warn!("Unable to refresh OAuth access token: {}", err);
+                    self.request_new_token().await?
                 }
-                Err(_) => (),
+            },
+            None => self.request_new_token().await?,
+        };
+
+        let value = new_token.value.clone();
+        *self.token.lock().unwrap() = Some(new_token);
+
+        Ok(value)
+    }
+
+    async fn request_new_token(&self) -> Result<AccessToken, anyhow::Error> {
+        match &self.auth {
+            AuthConfig::OAuth2 {
+                grant: OAuth2Grant::Password { user, password },
+                ..
+            } => {
+                self.request_token(&[
+                    ("grant_type", "password"),
+                    ("username", user),
+                    ("password", password),
+                ])
+                .await
+            }
+            _ => {
+                self.request_token(&[("grant_type", "client_credentials")])
+                    .await
             }
         }
     }
 
-    if user.eq("unknown") || pwd.eq("unknown") {
-        Err("SAP userid and/or password missing from .env file".to_owned())
-    } else {
-        Ok(general_purpose::STANDARD.encode(format!("{}:{}", user, pwd)))
+    async fn request_token(&self, params: &[(&str, &str)]) -> Result<AccessToken, anyhow::Error> {
+        let AuthConfig::OAuth2 {
+            token_url,
+            client_id,
+            client_secret,
+            scopes,
+            ..
+        } = &self.auth
+        else {
+            return Err(anyhow!(
+                "Access tokens are only used for OAuth authentication"
+            ));
+        };
+
+        let scope = scopes.join(" ");
+        let mut form: Vec<(&str, &str)> = params.to_vec();
+
+        if !scope.is_empty() {
+            form.push(("scope", &scope));
+        }
+
+        log::info!("POST: {}", token_url);
+
+        let response = self
+            .http_client
+            .post(token_url)
+            .basic_auth(client_id, Some(client_secret))
+            .form(&form)
+            .send()
+            .await?;
+
+        let http_status_code = response.status();
+        log::info!("HTTP Status code = {}", http_status_code);
+
+        if !http_status_code.is_success() {
+            return Err(anyhow!(
+                "OAuth token endpoint {} returned {}: {}",
+                token_url,
+                http_status_code,
+                response.text().await.unwrap_or_default()
+            ));
+        }
+
+        let token_response = response.json::<TokenResponse>().await.map_err(|err| {
+            anyhow!(
+                "Invalid response from OAuth token endpoint {}: {}",
+                token_url,
+                err
+            )
+        })?;
+
+        Ok(AccessToken::from_response(token_response))
     }
 }
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
@@ -0,0 +1,153 @@
+use super::*;
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+/// Records what the mock token endpoint has been asked for
+#[derive(Default)]
+struct TokenRequests {
+    count: AtomicUsize,
+    forms: Mutex<Vec<HashMap<String, String>>>,
+    authorization: Mutex<Option<String>>,
+}
+
+/// Start a local OAuth token endpoint that issues tokens valid for `expires_in` seconds
+fn start_mock_token_endpoint(expires_in: u64) -> (String, Arc<TokenRequests>) {
+    let requests = Arc::new(TokenRequests::default());
+    let app_requests = requests.clone();
+
+    let server = HttpServer::new(move || {
+        let requests = app_requests.clone();
+
+        App::new().route(
+            "/oauth/token",
+            web::post().to(
+                move |req: HttpRequest, form: web::Form<HashMap<String, String>>| {
+                    let requests = requests.clone();
+
+                    async move {
+                        let count = requests.count.fetch_add(1, Ordering::SeqCst) + 1;
+                        *requests.authorization.lock().unwrap() = req
+                            .headers()
+                            .get("Authorization")
+                            .map(|h| h.to_str().unwrap().to_owned());
+                        requests.forms.lock().unwrap().push(form.into_inner());
+
+                        HttpResponse::Ok().json(json!({
+                            "access_token": format!("token{}", count),
+                            "token_type": "bearer",
+                            "expires_in": expires_in,
+                            "refresh_token": format!("refresh{}", count)
+                        }))
+                    }
+                },
+            ),
+        )
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+
+    let token_url = format!("http://{}/oauth/token", server.addrs()[0]);
+    actix_rt::spawn(server.run());
+
+    (token_url, requests)
+}
+
+fn authenticator(auth: AuthConfig) -> Authenticator {
+    Authenticator::new(&auth, reqwest::Client::new())
+}
+
+fn oauth_config(token_url: &str, grant: OAuth2Grant) -> AuthConfig {
+    AuthConfig::OAuth2 {
+        token_url: token_url.to_owned(),
+        client_id: "my_client".to_owned(),
+        client_secret: "my_secret".to_owned(),
+        scopes: vec!["API_CATALOG".to_owned(), "read".to_owned()],
+        grant,
+    }
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[actix_web::test]
+pub async fn should_build_basic_authorization() {
+    let auth = authenticator(AuthConfig::Basic {
+        user: "user".to_owned(),
+        password: "secret".to_owned(),
+    });
+
+    assert_eq!(
+        auth.authorization().await.unwrap().as_deref(),
+        Some("Basic dXNlcjpzZWNyZXQ=")
+    );
+}
+
+#[actix_web::test]
+pub async fn should_cache_client_credentials_token() {
+    let (token_url, requests) = start_mock_token_endpoint(3600);
+    let auth = authenticator(oauth_config(&token_url, OAuth2Grant::ClientCredentials));
+
+    assert_eq!(
+        auth.authorization().await.unwrap().as_deref(),
+        Some("Bearer token1")
+    );
+    assert_eq!(
+        auth.authorization().await.unwrap().as_deref(),
+        Some("Bearer token1")
+    );
+    assert_eq!(requests.count.load(Ordering::SeqCst), 1);
+
+    let form = &requests.forms.lock().unwrap()[0];
+    assert_eq!(form["grant_type"], "client_credentials");
+    assert_eq!(form["scope"], "API_CATALOG read");
+    assert_eq!(
+        requests.authorization.lock().unwrap().as_deref(),
+        Some("Basic bXlfY2xpZW50Om15X3NlY3JldA==")
+    );
+}
+
+#[actix_web::test]
+pub async fn should_refresh_token_before_expiry() {
+    // A token that expires within the refresh margin must be renewed on every use
+    let (token_url, requests) = start_mock_token_endpoint(30);
+    let auth = authenticator(oauth_config(
+        &token_url,
+        OAuth2Grant::Password {
+            user: "user".to_owned(),
+            password: "secret".to_owned(),
+        },
+    ));
+
+    assert_eq!(
+        auth.authorization().await.unwrap().as_deref(),
+        Some("Bearer token1")
+    );
+    assert_eq!(
+        auth.authorization().await.unwrap().as_deref(),
+        Some("Bearer token2")
+    );
+
+    let forms = requests.forms.lock().unwrap();
+    assert_eq!(forms[0]["grant_type"], "password");
+    assert_eq!(forms[0]["username"], "user");
+    assert_eq!(forms[1]["grant_type"], "refresh_token");
+    assert_eq!(forms[1]["refresh_token"], "refresh1");
+}
+
+#[actix_web::test]
+pub async fn should_report_token_endpoint_failure() {
+    let (token_url, _requests) = start_mock_token_endpoint(3600);
+    let auth = authenticator(oauth_config(
+        &token_url.replace("/oauth/token", "/missing"),
+        OAuth2Grant::ClientCredentials,
+    ));
+
+    let err = auth.authorization().await.unwrap_err().to_string();
+    assert!(err.contains("returned 404"), "{}", err);
+}
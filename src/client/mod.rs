@@ -0,0 +1,870 @@
+use crate::{
+    catalogservice::{
+        Annotation, Catalog, CatalogserviceEntities, EntitySet, Service, Tag, Vocabulary,
+    },
+    config::{PayloadFormat, Profile},
+    expand::{inline_feeds, Expanded, ExpandedService, SERVICE_NAVIGATION},
+    json,
+    query::{Filter, Literal, Order, Query},
+    session::SapSession,
+};
+use chrono::NaiveDate;
+use futures_util::{pin_mut, stream, Stream, TryStreamExt};
+use parse_sap_atom_feed::{
+    atom::{
+        feed::{entry::Entry, Feed},
+        AtomService,
+    },
+    odata_error::ODataError,
+};
+use reqwest::{header::CONTENT_TYPE, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{
+    fmt,
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use url::Url;
+
+static NEXT_LINK_REL: &str = "next";
+static FORMAT_OPTION: &str = "$format";
+
+/// A system that answers `$format=json` with one of these has no JSON support, so the request is repeated in Atom
+static JSON_REJECTED_STATUS: [StatusCode; 4] = [
+    StatusCode::BAD_REQUEST,
+    StatusCode::NOT_ACCEPTABLE,
+    StatusCode::UNSUPPORTED_MEDIA_TYPE,
+    StatusCode::NOT_IMPLEMENTED,
+];
+
+/// Turns the entries of a feed into the items of a page.  The raw feed XML is passed for anything the Atom feed parser
+/// does not read.
+type EntryReader<T, E> = fn(&str, Vec<Entry<T>>) -> Result<Vec<E>, CatalogError>;
+
+/// Turns a JSON response into a page, given the page URL and the raw JSON
+type JsonReader<E> = fn(&str, &str) -> Result<Page<E>, CatalogError>;
+
+/// A response body in the format the Gateway actually used
+enum Payload {
+    Atom(String),
+    Json(String),
+}
+
+/// An OData V2 error in JSON format, `{"error": {"message": {"value": "..."}}}`
+#[derive(Debug, Deserialize)]
+struct JsonErrorResponse {
+    error: JsonError,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonError {
+    message: JsonErrorMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonErrorMessage {
+    value: String,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// The ways in which a catalog service request can fail
+#[derive(Debug)]
+pub enum CatalogError {
+    /// The request could not be sent, or its response could not be read
+    Request(anyhow::Error),
+    /// The Gateway responded with an error status.  Where the response is an OData error, the message is taken from it;
+    /// otherwise, it is the response body
+    Status { status: StatusCode, message: String },
+    /// The response is not in the expected format
+    Parse(String),
+    /// The profile does not allow this request
+    NotPermitted(String),
+}
+
+impl CatalogError {
+    fn from_response(status: StatusCode, body: &str) -> CatalogError {
+        CatalogError::Status {
+            status,
+            message: match ODataError::from_str(body) {
+                Ok(odata_error) => odata_error.message,
+                Err(_) => match serde_json::from_str::<JsonErrorResponse>(body) {
+                    Ok(json_error) => json_error.error.message.value,
+                    Err(_) => body.to_owned(),
+                },
+            },
+        }
+    }
+
+    /// The HTTP status returned by the Gateway, if it responded at all
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            CatalogError::Status { status, .. } => Some(*status),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Request(err) => write!(f, "{}", err),
+            CatalogError::Status { status, message } => write!(f, "{} ({})", message, status),
+            CatalogError::Parse(msg) => write!(f, "{}", msg),
+            CatalogError::NotPermitted(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Criteria for finding services in `ServiceCollection`.  Blank criteria are ignored.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ServiceSearch {
+    /// Free text searched for by the Gateway (`search=`)
+    pub search: String,
+    /// Text contained in the service ID
+    pub id: String,
+    /// Text contained in the service title
+    pub title: String,
+    /// Text contained in the author's name
+    pub author: String,
+    /// Text contained in the technical service name
+    pub technical_service_name: String,
+    /// The exact technical service version
+    pub technical_service_version: String,
+    /// The earliest update date, as `YYYY-MM-DD`
+    pub updated_since: String,
+}
+
+impl ServiceSearch {
+    pub fn is_empty(&self) -> bool {
+        [
+            &self.search,
+            &self.id,
+            &self.title,
+            &self.author,
+            &self.technical_service_name,
+            &self.technical_service_version,
+            &self.updated_since,
+        ]
+        .iter()
+        .all(|criterion| criterion.trim().is_empty())
+    }
+
+    /// A query on `ServiceCollection` for the services matching every given criterion, ordered by ID
+    pub fn to_query(&self) -> Result<Query, String> {
+        let mut query =
+            entity_set(CatalogserviceEntities::ServiceCollection).order_by("ID", Order::Ascending);
+
+        for (property, text) in [
+            ("ID", &self.id),
+            ("Title", &self.title),
+            ("Author", &self.author),
+            ("TechnicalServiceName", &self.technical_service_name),
+        ] {
+            if !text.trim().is_empty() {
+                query = query.filter(Filter::substring_of(property, text.trim()));
+            }
+        }
+
+        if !self.technical_service_version.trim().is_empty() {
+            let version = self
+                .technical_service_version
+                .trim()
+                .parse::<i16>()
+                .map_err(|_| {
+                    format!(
+                        "Invalid technical service version '{}'",
+                        self.technical_service_version
+                    )
+                })?;
+            query = query.filter(Filter::eq("TechnicalServiceVersion", version));
+        }
+
+        if !self.updated_since.trim().is_empty() {
+            let date =
+                NaiveDate::parse_from_str(self.updated_since.trim(), "%Y-%m-%d").map_err(|_| {
+                    format!("Invalid date '{}': expected YYYY-MM-DD", self.updated_since)
+                })?;
+            query = query.filter(Filter::ge("UpdatedDate", date.and_time(Default::default())));
+        }
+
+        if !self.search.trim().is_empty() {
+            query = query.search(self.search.trim());
+        }
+
+        Ok(query)
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// One page of a collection, as returned by the Gateway
+#[derive(Debug)]
+pub struct Page<T> {
+    pub entries: Vec<T>,
+    /// The number of entries in the whole collection, if `$inlinecount=allpages` was requested
+    pub total_count: Option<usize>,
+    /// Where the Gateway has more entries than it returned, the URL of the next page (typically with a `$skiptoken`)
+    pub next_url: Option<String>,
+}
+
+/// Every entry read from a collection
+#[derive(Debug)]
+pub struct EntityList<T> {
+    pub entries: Vec<T>,
+    /// The number of entries in the whole collection, if `$inlinecount=allpages` was requested
+    pub total_count: Option<usize>,
+}
+
+/// The `<m:count>` element of a feed
+#[derive(Debug, Deserialize)]
+struct FeedCount {
+    count: Option<usize>,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Reads the catalog service of one SAP system
+///
+/// All requests share one logon session with that system (see [`SapSession`]).  Where the profile asks for JSON,
+/// entities are read with `$format=json`, except for collections read with `$expand`, which are always read as Atom.
+#[derive(Debug)]
+pub struct CatalogClient {
+    session: SapSession,
+    service_url: String,
+    json_rejected: AtomicBool,
+}
+
+impl CatalogClient {
+    pub fn new(profile: &Profile) -> Result<CatalogClient, String> {
+        Ok(CatalogClient {
+            session: SapSession::new(profile)?,
+            service_url: profile.catalog_service_url(),
+            json_rejected: AtomicBool::new(false),
+        })
+    }
+
+    /// Point the client at a mock Gateway, which does not use TLS
+    #[cfg(test)]
+    pub(crate) fn with_service_url(mut self, service_url: String) -> CatalogClient {
+        self.session = self.session.with_csrf_url(service_url.clone());
+        self.service_url = service_url;
+        self
+    }
+
+    pub fn profile(&self) -> &Profile {
+        self.session.profile()
+    }
+
+    pub fn session(&self) -> &SapSession {
+        &self.session
+    }
+
+    /// The URL of the catalog service, ending with `/`
+    pub fn service_url(&self) -> &str {
+        &self.service_url
+    }
+
+    /// The catalog service's own service document
+    pub async fn service_document(&self) -> Result<AtomService, CatalogError> {
+        let raw_xml = self.fetch_text(&self.service_url).await?;
+
+        AtomService::from_str(&raw_xml).map_err(|err| {
+            CatalogError::Parse(format!(
+                "Invalid service document {}: {}",
+                self.service_url, err
+            ))
+        })
+    }
+
+    pub async fn catalogs(&self) -> Result<Vec<Catalog>, CatalogError> {
+        self.fetch_entities(&entity_set(CatalogserviceEntities::CatalogCollection))
+            .await
+    }
+
+    /// The services in the catalog with the given ID
+    pub async fn services(&self, catalog_id: &str) -> Result<Vec<Service>, CatalogError> {
+        self.fetch_entities(
+            &entity_set(CatalogserviceEntities::CatalogCollection)
+                .key(catalog_id)
+                .navigate("Services"),
+        )
+        .await
+    }
+
+    /// The services in the catalog with the given ID, each read together with its entity sets, tags and annotation files
+    pub async fn expanded_services(
+        &self,
+        catalog_id: &str,
+    ) -> Result<Vec<ExpandedService>, CatalogError> {
+        let query = SERVICE_NAVIGATION.iter().fold(
+            entity_set(CatalogserviceEntities::CatalogCollection)
+                .key(catalog_id)
+                .navigate("Services"),
+            |query, navigation| query.expand(navigation),
+        );
+
+        self.read_all_expanded::<Service>(&query)
+            .await?
+            .entries
+            .into_iter()
+            .map(ExpandedService::try_from)
+            .collect()
+    }
+
+    pub async fn service(&self, service_id: &str) -> Result<Service, CatalogError> {
+        self.fetch_entity(&entity_set(CatalogserviceEntities::ServiceCollection).key(service_id))
+            .await
+    }
+
+    /// Call the `BestMatchingService` function import, which finds the service with the given technical name whose
+    /// version is the highest one within the given range
+    pub async fn best_matching_service(
+        &self,
+        technical_service_name: &str,
+        version_min: i16,
+        version_max: i16,
+    ) -> Result<Service, CatalogError> {
+        self.fetch_entity(
+            &Query::function_import("BestMatchingService")
+                .parameter("TechnicalServiceName", technical_service_name)
+                .parameter("TechnicalServiceVersionMin", version_min)
+                .parameter("TechnicalServiceVersionMax", version_max),
+        )
+        .await
+    }
+
+    /// Call the `ClearMetadataCacheForService` function import, so that the Gateway rebuilds the metadata of the
+    /// service with the given ID from its backend model
+    ///
+    /// Since this affects every user of the system, it is refused unless the profile sets `allow_cache_clear`.  The
+    /// function import is called with POST and a CSRF token; a Gateway that only accepts it with GET, as the catalog
+    /// service's own metadata declares, answers 405 and is then called with GET.
+    pub async fn clear_metadata_cache(&self, service_id: &str) -> Result<Service, CatalogError> {
+        if !self.profile().allow_cache_clear {
+            return Err(CatalogError::NotPermitted(format!(
+                "Clearing the metadata cache is not enabled for SAP system profile {}",
+                self.profile().name
+            )));
+        }
+
+        log::warn!(
+            "Clearing the metadata cache of service {} in SAP system profile {}",
+            service_id,
+            self.profile().name
+        );
+        let entry_url = self.url(
+            &Query::function_import("ClearMetadataCacheForService")
+                .parameter("ServiceID", service_id),
+        );
+
+        let raw_xml = match self.post_text(&entry_url).await {
+            Err(CatalogError::Status {
+                status: StatusCode::METHOD_NOT_ALLOWED,
+                ..
+            }) => {
+                log::warn!("POST {} is not allowed: retrying with GET", entry_url);
+                self.fetch_text(&entry_url).await?
+            }
+            outcome => outcome?,
+        };
+
+        read_entry(&entry_url, &raw_xml)
+    }
+
+    pub async fn entity_sets(&self, service_id: &str) -> Result<Vec<EntitySet>, CatalogError> {
+        self.fetch_entities(
+            &entity_set(CatalogserviceEntities::ServiceCollection)
+                .key(service_id)
+                .navigate("EntitySets"),
+        )
+        .await
+    }
+
+    pub async fn tags(&self) -> Result<Vec<Tag>, CatalogError> {
+        self.fetch_entities(&entity_set(CatalogserviceEntities::TagCollection))
+            .await
+    }
+
+    /// The services that carry the tag with the given ID
+    pub async fn tag_services(&self, tag_id: &str) -> Result<Vec<Service>, CatalogError> {
+        self.fetch_entities(
+            &entity_set(CatalogserviceEntities::TagCollection)
+                .key(tag_id)
+                .navigate("Services"),
+        )
+        .await
+    }
+
+    pub async fn annotations(&self) -> Result<Vec<Annotation>, CatalogError> {
+        self.fetch_entities(&entity_set(CatalogserviceEntities::Annotations))
+            .await
+    }
+
+    /// The services that use the given version of an annotation file
+    pub async fn annotation_services(
+        &self,
+        technical_name: &str,
+        version: &str,
+    ) -> Result<Vec<Service>, CatalogError> {
+        self.fetch_entities(&annotation(technical_name, version).navigate("Services"))
+            .await
+    }
+
+    /// The raw XML content of an annotation file, read from its media resource
+    pub async fn annotation_file(
+        &self,
+        technical_name: &str,
+        version: &str,
+    ) -> Result<String, CatalogError> {
+        self.fetch_text(&self.url(&annotation(technical_name, version).media_resource()))
+            .await
+    }
+
+    pub async fn vocabularies(&self) -> Result<Vec<Vocabulary>, CatalogError> {
+        self.fetch_entities(&entity_set(CatalogserviceEntities::Vocabularies))
+            .await
+    }
+
+    /// The raw XML content of a vocabulary file, read from its media resource
+    pub async fn vocabulary_file(&self, vocabulary: &Vocabulary) -> Result<String, CatalogError> {
+        let query = entity_set(CatalogserviceEntities::Vocabularies)
+            .keys(&[
+                (
+                    "TechnicalName",
+                    Literal::from(vocabulary.technical_name.as_str()),
+                ),
+                ("Version", Literal::from(vocabulary.version.as_str())),
+                ("SAP__Origin", Literal::from(vocabulary.sap_origin.as_str())),
+            ])
+            .media_resource();
+
+        self.fetch_text(&self.url(&query)).await
+    }
+
+    /// The raw service document of a service on the same SAP system, read from its service URL, together with the
+    /// status of the successful response
+    pub async fn service_document_at(
+        &self,
+        service_url: &str,
+    ) -> Result<(StatusCode, String), CatalogError> {
+        self.check_same_system(service_url)?;
+        self.fetch_response(service_url).await
+    }
+
+    /// The first `top` entries of an entity set of a service on the same SAP system, as a raw Atom feed
+    pub async fn entity_set_feed_at(
+        &self,
+        service_url: &str,
+        entity_set: &str,
+        top: usize,
+    ) -> Result<String, CatalogError> {
+        self.check_same_system(service_url)?;
+        let service_url = format!("{}/", service_url.trim_end_matches('/'));
+        self.fetch_text(&Query::entity_set(entity_set).top(top).to_url(&service_url))
+            .await
+    }
+
+    /// The raw EDMX metadata document of a service
+    pub async fn metadata(&self, service: &Service) -> Result<String, CatalogError> {
+        self.metadata_at(&service.metadata_url).await
+    }
+
+    /// The raw EDMX metadata document found at the given `$metadata` URL, which must belong to this SAP system
+    pub async fn metadata_at(&self, metadata_url: &str) -> Result<String, CatalogError> {
+        self.check_same_system(metadata_url)?;
+        self.fetch_text(metadata_url).await
+    }
+
+    // - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+    /// The absolute URL of a query against the catalog service
+    pub fn url(&self, query: &Query) -> String {
+        query.to_url(&self.service_url)
+    }
+
+    /// Read a collection page by page, following the feed's `next` links
+    ///
+    /// When the query has a `$top`, no more than that many entries are returned, however the Gateway pages them
+    pub fn pages<'a, T>(
+        &'a self,
+        query: &Query,
+    ) -> impl Stream<Item = Result<Page<T>, CatalogError>> + 'a
+    where
+        T: DeserializeOwned + 'a,
+    {
+        self.pages_with(query, entities::<T>, Some(json::read_page::<T>))
+    }
+
+    /// Read every page of a collection
+    pub async fn read_all<T>(&self, query: &Query) -> Result<EntityList<T>, CatalogError>
+    where
+        T: DeserializeOwned,
+    {
+        collect_pages(self.pages::<T>(query)).await
+    }
+
+    /// Read every page of a collection whose query has `$expand` options, keeping the inline feeds of each entry
+    pub async fn read_all_expanded<T>(
+        &self,
+        query: &Query,
+    ) -> Result<EntityList<Expanded<T>>, CatalogError>
+    where
+        T: DeserializeOwned,
+    {
+        collect_pages(self.pages_with(query, expanded_entities::<T>, None)).await
+    }
+
+    /// Read a single page of a collection
+    pub async fn fetch_page<T>(&self, page_url: &str) -> Result<Page<T>, CatalogError>
+    where
+        T: DeserializeOwned,
+    {
+        self.fetch_page_with(page_url, entities::<T>, Some(json::read_page::<T>))
+            .await
+    }
+
+    // - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+    fn pages_with<'a, T, E>(
+        &'a self,
+        query: &Query,
+        read_entries: EntryReader<T, E>,
+        read_json: Option<JsonReader<E>>,
+    ) -> impl Stream<Item = Result<Page<E>, CatalogError>> + 'a
+    where
+        T: DeserializeOwned + 'a,
+        E: 'a,
+    {
+        let first_url = self.url(query);
+        let max_entries = query.max_entries();
+
+        stream::try_unfold((Some(first_url), 0), move |(page_url, read)| async move {
+            let Some(page_url) = page_url else {
+                return Ok(None);
+            };
+
+            if max_entries.is_some_and(|top| read >= top) {
+                return Ok(None);
+            }
+
+            let mut page = self
+                .fetch_page_with(&page_url, read_entries, read_json)
+                .await?;
+
+            if let Some(top) = max_entries {
+                page.entries.truncate(top - read);
+            }
+
+            let read = read + page.entries.len();
+            let next_url = page
+                .next_url
+                .clone()
+                .filter(|next_url| *next_url != page_url);
+
+            Ok(Some((page, (next_url, read))))
+        })
+    }
+
+    async fn fetch_page_with<T, E>(
+        &self,
+        page_url: &str,
+        read_entries: EntryReader<T, E>,
+        read_json: Option<JsonReader<E>>,
+    ) -> Result<Page<E>, CatalogError>
+    where
+        T: DeserializeOwned,
+    {
+        log::info!("---> fetch_page()");
+        // Every page is requested with the profile's credentials, so a next link must not lead to another system
+        self.check_same_system(page_url)?;
+
+        let json_reader = read_json.filter(|_| self.wants_json());
+
+        let raw_xml = match (
+            self.fetch_payload(page_url, json_reader.is_some()).await?,
+            json_reader,
+        ) {
+            (Payload::Json(raw_json), Some(read_json)) => {
+                // A relative __next link is resolved against the page URL
+                let mut page = read_json(page_url, &raw_json)?;
+                page.next_url = page.next_url.map(|href| resolve_url(page_url, &href));
+
+                log::info!("<--- fetch_page()");
+                return Ok(page);
+            }
+            (Payload::Json(_), None) => {
+                return Err(CatalogError::Parse(format!(
+                    "Unexpected JSON response from {}",
+                    page_url
+                )))
+            }
+            (Payload::Atom(raw_xml), _) => raw_xml,
+        };
+
+        let feed = match Feed::<T>::from_str(&raw_xml) {
+            Ok(feed) => feed,
+            Err(err) => {
+                log::error!("<--- fetch_page() ERROR in XML deserialization");
+                return Err(CatalogError::Parse(format!(
+                    "Invalid feed {}: {}",
+                    page_url, err
+                )));
+            }
+        };
+
+        // The Atom feed parser does not read <m:count>
+        let total_count = quick_xml::de::from_str::<FeedCount>(&raw_xml)
+            .ok()
+            .and_then(|feed_count| feed_count.count);
+
+        // A relative next link is resolved against the feed's xml:base
+        let base_url = feed.xml_base.as_deref().unwrap_or(page_url);
+        let next_url = feed
+            .links
+            .iter()
+            .find(|link| link.rel == NEXT_LINK_REL)
+            .map(|link| resolve_url(base_url, &link.href));
+
+        log::info!("<--- fetch_page()");
+        Ok(Page {
+            entries: read_entries(&raw_xml, feed.entries.unwrap_or_default())?,
+            total_count,
+            next_url,
+        })
+    }
+
+    async fn fetch_entities<T>(&self, query: &Query) -> Result<Vec<T>, CatalogError>
+    where
+        T: DeserializeOwned,
+    {
+        Ok(self.read_all::<T>(query).await?.entries)
+    }
+
+    async fn fetch_entity<T>(&self, query: &Query) -> Result<T, CatalogError>
+    where
+        T: DeserializeOwned,
+    {
+        let entry_url = self.url(query);
+        match self.fetch_payload(&entry_url, self.wants_json()).await? {
+            Payload::Json(raw_json) => json::read_entry(&entry_url, &raw_json),
+            Payload::Atom(raw_xml) => read_entry(&entry_url, &raw_xml),
+        }
+    }
+
+    /// Whether entities are requested as JSON: the profile asks for it, and the system has not rejected it
+    fn wants_json(&self) -> bool {
+        self.profile().format == PayloadFormat::Json && !self.json_rejected.load(Ordering::Relaxed)
+    }
+
+    /// Read an entity or a collection, in JSON if `json` is set.  Once a system has rejected `$format=json`, the
+    /// request is repeated in Atom, and all further requests are sent in Atom.
+    async fn fetch_payload(&self, url: &str, json: bool) -> Result<Payload, CatalogError> {
+        if !json {
+            return Ok(Payload::Atom(self.fetch_text(url).await?));
+        }
+
+        let json_url = json_format_url(url);
+        let response = self
+            .session
+            .get(&json_url)
+            .await
+            .map_err(CatalogError::Request)?;
+        let status = response.status();
+        let is_json = response
+            .headers()
+            .get(CONTENT_TYPE)
+            .and_then(|content_type| content_type.to_str().ok())
+            .is_some_and(|content_type| content_type.contains("json"));
+        let body = response
+            .text()
+            .await
+            .map_err(|err| CatalogError::Request(err.into()))?;
+
+        // Some systems ignore $format and answer in Atom regardless
+        if status.is_success() {
+            return Ok(if is_json {
+                Payload::Json(body)
+            } else {
+                Payload::Atom(body)
+            });
+        }
+
+        if !JSON_REJECTED_STATUS.contains(&status) {
+            log::error!("GET {} returned {}", json_url, status);
+            return Err(CatalogError::from_response(status, &body));
+        }
+
+        log::warn!(
+            "GET {} returned {}: retrying in Atom format",
+            json_url,
+            status
+        );
+        let raw_xml = self.fetch_text(url).await?;
+
+        // Only now is it clear that the JSON request failed because of its format
+        if !self.json_rejected.swap(true, Ordering::Relaxed) {
+            log::warn!(
+                "SAP system profile {} does not support $format=json: using Atom from now on",
+                self.profile().name
+            );
+        }
+
+        Ok(Payload::Atom(raw_xml))
+    }
+
+    /// Every request carries this system's credentials, so a URL supplied from elsewhere is only requested if its
+    /// scheme, host and port are those of the catalog service
+    fn check_same_system(&self, url: &str) -> Result<(), CatalogError> {
+        let origin = |url: &str| Url::parse(url).map(|url| url.origin());
+
+        match (origin(url), origin(&self.service_url)) {
+            (Ok(requested), Ok(own)) if requested == own => Ok(()),
+            _ => Err(CatalogError::NotPermitted(format!(
+                "{} does not belong to SAP system profile {}",
+                url,
+                self.profile().name
+            ))),
+        }
+    }
+
+    /// Read the body of a successful response
+    async fn fetch_text(&self, url: &str) -> Result<String, CatalogError> {
+        self.fetch_response(url).await.map(|(_, body)| body)
+    }
+
+    /// Read the status and body of a successful response
+    async fn fetch_response(&self, url: &str) -> Result<(StatusCode, String), CatalogError> {
+        let response = self.session.get(url).await.map_err(CatalogError::Request)?;
+        success_body("GET", url, response).await
+    }
+
+    /// Send a POST request, and read the body of a successful response
+    async fn post_text(&self, url: &str) -> Result<String, CatalogError> {
+        let response = self
+            .session
+            .post(url)
+            .await
+            .map_err(CatalogError::Request)?;
+        success_body("POST", url, response)
+            .await
+            .map(|(_, body)| body)
+    }
+}
+
+/// The status and body of a response, if its status is a success
+async fn success_body(
+    method: &str,
+    url: &str,
+    response: reqwest::Response,
+) -> Result<(StatusCode, String), CatalogError> {
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|err| CatalogError::Request(err.into()))?;
+
+    if status.is_success() {
+        Ok((status, body))
+    } else {
+        log::error!("{} {} returned {}", method, url, status);
+        Err(CatalogError::from_response(status, &body))
+    }
+}
+
+/// The properties of a single Atom entry
+fn read_entry<T>(entry_url: &str, raw_xml: &str) -> Result<T, CatalogError>
+where
+    T: DeserializeOwned,
+{
+    Entry::<T>::from_str(raw_xml)
+        .map_err(|err| CatalogError::Parse(format!("Invalid entry {}: {}", entry_url, err)))
+        .and_then(|entry| {
+            entry_properties(entry).ok_or_else(|| {
+                CatalogError::Parse(format!("Entry {} has no properties", entry_url))
+            })
+        })
+}
+
+fn entity_set(entity_set: CatalogserviceEntities) -> Query {
+    Query::entity_set(entity_set.variant_name())
+}
+
+/// Annotation files are identified by their technical name together with their version
+fn annotation(technical_name: &str, version: &str) -> Query {
+    entity_set(CatalogserviceEntities::Annotations).keys(&[
+        ("TechnicalName", Literal::from(technical_name)),
+        ("Version", Literal::from(version)),
+    ])
+}
+
+fn json_format_url(url: &str) -> String {
+    if url.contains(&format!("{}=", FORMAT_OPTION)) {
+        url.to_owned()
+    } else if url.contains('?') {
+        format!("{}&{}=json", url, FORMAT_OPTION)
+    } else {
+        format!("{}?{}=json", url, FORMAT_OPTION)
+    }
+}
+
+fn resolve_url(base_url: &str, href: &str) -> String {
+    match Url::parse(base_url).and_then(|base| base.join(href)) {
+        Ok(url) => url.to_string(),
+        Err(_) => href.to_owned(),
+    }
+}
+
+/// The properties of a media entity (such as an `Annotation`) are found outside the entry's content
+pub(crate) fn entry_properties<T>(entry: Entry<T>) -> Option<T> {
+    entry.content.properties.or(entry.properties)
+}
+
+fn entities<T>(_raw_xml: &str, entries: Vec<Entry<T>>) -> Result<Vec<T>, CatalogError> {
+    Ok(entries.into_iter().filter_map(entry_properties).collect())
+}
+
+/// Every top-level entry is paired with the inline feeds found inside it
+fn expanded_entities<T>(
+    raw_xml: &str,
+    entries: Vec<Entry<T>>,
+) -> Result<Vec<Expanded<T>>, CatalogError> {
+    let inline_feeds = inline_feeds(raw_xml).map_err(CatalogError::Parse)?;
+
+    if inline_feeds.len() != entries.len() {
+        return Err(CatalogError::Parse(format!(
+            "Found inline feeds for {} entries in a feed of {} entries",
+            inline_feeds.len(),
+            entries.len()
+        )));
+    }
+
+    Ok(entries
+        .into_iter()
+        .zip(inline_feeds)
+        .filter_map(|(entry, inline_feeds)| {
+            entry_properties(entry).map(|entity| Expanded::new(entity, inline_feeds))
+        })
+        .collect())
+}
+
+async fn collect_pages<E>(
+    pages: impl Stream<Item = Result<Page<E>, CatalogError>>,
+) -> Result<EntityList<E>, CatalogError> {
+    pin_mut!(pages);
+
+    let mut list = EntityList {
+        entries: Vec::new(),
+        total_count: None,
+    };
+
+    while let Some(page) = pages.try_next().await? {
+        list.total_count = list.total_count.or(page.total_count);
+        list.entries.extend(page.entries);
+    }
+
+    Ok(list)
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
@@ -0,0 +1,508 @@
+use super::*;
+use crate::config::{AuthConfig, PayloadFormat};
+use actix_web::{http::Method, web, App, HttpRequest, HttpResponse, HttpServer};
+use futures_util::StreamExt;
+use std::{collections::HashMap, fs};
+
+static CATALOG_PATH: &str = "/sap/opu/odata/iwfnd/catalogservice;v=2";
+static FEED_XML_BASE: &str =
+    "https://SAPES5.SAPDEVCENTER.COM:443/sap/opu/odata/iwfnd/catalogservice;v=2/";
+static PAGE_SIZE: usize = 25;
+static CSRF_TOKEN: &str = "mock-token";
+
+/// Serve `ServiceCollection.xml` in pages, the way a Gateway with server-side paging does
+fn service_collection_page(req: &HttpRequest) -> HttpResponse {
+    let query = web::Query::<HashMap<String, String>>::from_query(req.query_string())
+        .unwrap()
+        .into_inner();
+    let xml = fs::read_to_string("./test_data/ServiceCollection.xml").unwrap();
+    let first = xml.find("<entry>").unwrap();
+    let last = xml.rfind("</entry>").unwrap() + "</entry>".len();
+    let entries: Vec<&str> = xml[first..last].split_inclusive("</entry>").collect();
+
+    let skip = query
+        .get("$skiptoken")
+        .or(query.get("$skip"))
+        .map_or(0, |skip| skip.parse::<usize>().unwrap());
+    let end = (skip + PAGE_SIZE).min(entries.len());
+
+    let own_base = format!("http://{}{}/", req.connection_info().host(), CATALOG_PATH);
+    let mut page = xml[..first].replace(FEED_XML_BASE, &own_base);
+
+    if query.get("$inlinecount").map(String::as_str) == Some("allpages") {
+        page.push_str(&format!("<m:count>{}</m:count>", entries.len()));
+    }
+
+    page.push_str(&entries[skip..end].concat());
+
+    if end < entries.len() {
+        page.push_str(&format!(
+            "<link href=\"ServiceCollection?$skiptoken={}\" rel=\"next\"/>",
+            end
+        ));
+    }
+
+    page.push_str("</feed>");
+
+    HttpResponse::Ok()
+        .content_type("application/atom+xml")
+        .body(page)
+}
+
+/// Serve the files in `test_data` as if they were the catalog service of a Gateway
+async fn gateway(req: HttpRequest) -> HttpResponse {
+    let path = req.path().strip_prefix(CATALOG_PATH).unwrap_or_default();
+    let csrf_header = req
+        .headers()
+        .get("X-CSRF-Token")
+        .and_then(|value| value.to_str().ok());
+
+    if req.method() == Method::POST && csrf_header != Some(CSRF_TOKEN) {
+        return HttpResponse::Forbidden().body("CSRF token validation failed");
+    }
+
+    // Only the TagCollection is available in JSON
+    if req.query_string().contains("$format=json") {
+        return match path {
+            "/TagCollection" => HttpResponse::Ok()
+                .content_type("application/json")
+                .body(fs::read("./test_data/TagCollection.json").unwrap()),
+            _ => HttpResponse::BadRequest()
+                .content_type("application/json")
+                .body(r#"{"error": {"code": "/IWFND/CM_MGW/000", "message": {"lang": "en", "value": "Unsupported format"}}}"#),
+        };
+    }
+
+    let file_name = match path {
+        "/" => "CatalogService.xml",
+        "/CatalogCollection" => "CatalogCollection.xml",
+        "/CatalogCollection('ES5')/Services" if req.query_string().contains("$expand") => {
+            "ServiceCollectionExpanded.xml"
+        }
+        "/ServiceCollection" | "/CatalogCollection('ES5')/Services" => {
+            return service_collection_page(&req)
+        }
+        "/ForeignServiceCollection" => {
+            // The xml:base still names the real Gateway, so the next link leads away from the mock
+            let xml = fs::read_to_string("./test_data/ServiceCollection.xml").unwrap();
+            return HttpResponse::Ok()
+                .content_type("application/atom+xml")
+                .body(xml.replace(
+                    "</feed>",
+                    "<link href=\"ServiceCollection?$skiptoken=25\" rel=\"next\"/></feed>",
+                ));
+        }
+        "/ServiceCollection('ZPDCDS_SRV_0001')/EntitySets" => "EntitySetCollection.xml",
+        "/TagCollection" => "TagCollection.xml",
+        "/TagCollection('CDS.SEPMRA_C_PO_CURRENCY.SEPMRA_C_PO_CURRENCY')/Services" => {
+            "TagServices.xml"
+        }
+        "/BestMatchingService"
+            if req
+                .query_string()
+                .contains("TechnicalServiceName=%27ZPDCDS_SRV%27") =>
+        {
+            "BestMatchingService.xml"
+        }
+        // This Gateway accepts ClearMetadataCacheForService with POST, except for one service
+        "/ClearMetadataCacheForService"
+            if req.query_string() == "ServiceID=%27ZPDCDS_SRV_0001%27"
+                && req.method() == Method::POST =>
+        {
+            "BestMatchingService.xml"
+        }
+        "/ClearMetadataCacheForService"
+            if req.query_string() == "ServiceID=%27ZGET_ONLY_SRV_0001%27"
+                && req.method() == Method::GET =>
+        {
+            "BestMatchingService.xml"
+        }
+        "/ClearMetadataCacheForService" => {
+            return HttpResponse::MethodNotAllowed()
+                .content_type("application/xml")
+                .body(fs::read("./test_data/ODataError.xml").unwrap())
+        }
+        "/Annotations" => "Annotations.xml",
+        "/Annotations(TechnicalName='ZPDCDS_ANNO_MDL',Version='0001')/Services" => {
+            "AnnotationServices.xml"
+        }
+        "/Annotations(TechnicalName='ZPDCDS_ANNO_MDL',Version='0001')/$value" => {
+            "ZPDCDS_ANNO_MDL.xml"
+        }
+        "/Vocabularies" => "Vocabularies.xml",
+        "/Vocabularies(TechnicalName='%2FIWBEP%2FVOC_UI',Version='0001',SAP__Origin='LOCAL')/$value" => {
+            "VOC_UI.xml"
+        }
+        "/$metadata" => {
+            return HttpResponse::Ok().body(fs::read("./odata/catalogservice.xml").unwrap())
+        }
+        _ => {
+            return HttpResponse::NotFound()
+                .content_type("application/xml")
+                .body(fs::read("./test_data/ODataError.xml").unwrap())
+        }
+    };
+
+    let mut response = HttpResponse::Ok();
+
+    if csrf_header == Some("Fetch") {
+        response.insert_header(("X-CSRF-Token", CSRF_TOKEN));
+    }
+
+    response
+        .content_type("application/atom+xml")
+        .body(fs::read(format!("./test_data/{}", file_name)).unwrap())
+}
+
+fn start_mock_gateway() -> CatalogClient {
+    start_mock_gateway_with(|_| {})
+}
+
+/// Start the mock Gateway and a client for a test profile, adjusted by `configure`
+fn start_mock_gateway_with(configure: impl FnOnce(&mut Profile)) -> CatalogClient {
+    let server = HttpServer::new(|| App::new().default_service(web::to(gateway)))
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+
+    let addr = server.addrs()[0];
+    actix_rt::spawn(server.run());
+
+    let mut profile = Profile {
+        name: "TEST".to_owned(),
+        hostname: "127.0.0.1".to_owned(),
+        port: Some(addr.port()),
+        client: None,
+        auth: AuthConfig::Basic {
+            user: "user".to_owned(),
+            password: "secret".to_owned(),
+        },
+        ca_bundle: None,
+        catalog_path: CATALOG_PATH.to_owned(),
+        format: PayloadFormat::Atom,
+        allow_cache_clear: true,
+    };
+    configure(&mut profile);
+
+    CatalogClient::new(&profile)
+        .unwrap()
+        .with_service_url(format!("http://{}{}/", addr, CATALOG_PATH))
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[actix_web::test]
+pub async fn should_read_catalogs_and_services() {
+    let client = start_mock_gateway();
+
+    let srv_doc = client.service_document().await.unwrap();
+    assert!(srv_doc
+        .workspace
+        .collections
+        .iter()
+        .any(|c| c.href == "CatalogCollection"));
+
+    let catalogs = client.catalogs().await.unwrap();
+    assert_eq!(catalogs.len(), 1);
+    assert_eq!(catalogs[0].id, "ES5");
+
+    let services = client.services(&catalogs[0].id).await.unwrap();
+    assert_eq!(services.len(), 60);
+    assert_eq!(services[2].id, "WDR_ADAPT_UI_SRV_0001");
+
+    let entity_sets = client.entity_sets("ZPDCDS_SRV_0001").await.unwrap();
+    assert_eq!(entity_sets.len(), 12);
+}
+
+#[actix_web::test]
+pub async fn should_read_tags_and_annotations() {
+    let client = start_mock_gateway();
+
+    assert_eq!(client.tags().await.unwrap().len(), 8);
+
+    let services = client
+        .tag_services("CDS.SEPMRA_C_PO_CURRENCY.SEPMRA_C_PO_CURRENCY")
+        .await
+        .unwrap();
+    assert_eq!(services.len(), 1);
+    assert_eq!(services[0].id, "ZPDCDS_SRV_0001");
+
+    // Annotations are media entities, so their properties are outside the entry content
+    let annotations = client.annotations().await.unwrap();
+    assert_eq!(annotations.len(), 16);
+    assert!(annotations.iter().all(|a| !a.technical_name.is_empty()));
+}
+
+#[actix_web::test]
+pub async fn should_expand_service_navigation() {
+    let client = start_mock_gateway();
+    let services = client.expanded_services("ES5").await.unwrap();
+
+    assert_eq!(services.len(), 2);
+    assert_eq!(services[0].entity_sets.as_ref().unwrap().len(), 2);
+    assert_eq!(
+        services[1].tags.as_ref().unwrap()[0].id,
+        "CDS.SEPMRA_C_POAPV_SUPPLIER.SEPMRA_C_POAPV_SUPPLIER"
+    );
+}
+
+#[test]
+pub fn should_reject_feed_whose_inline_feeds_do_not_match_its_entries() {
+    let raw_xml = fs::read_to_string("./test_data/ServiceCollectionExpanded.xml").unwrap();
+
+    assert!(matches!(
+        expanded_entities::<Service>(&raw_xml, Vec::new()),
+        Err(CatalogError::Parse(_))
+    ));
+}
+
+#[actix_web::test]
+pub async fn should_read_annotation_file_and_its_services() {
+    let client = start_mock_gateway();
+
+    let services = client
+        .annotation_services("ZPDCDS_ANNO_MDL", "0001")
+        .await
+        .unwrap();
+    assert_eq!(services.len(), 1);
+    assert_eq!(services[0].id, "ZPDCDS_SRV_0001");
+
+    let raw_xml = client
+        .annotation_file("ZPDCDS_ANNO_MDL", "0001")
+        .await
+        .unwrap();
+    assert!(
+        raw_xml.contains("<Annotation Term=\"UI.LineItem\">"),
+        "{}",
+        raw_xml
+    );
+
+    let err = client
+        .annotation_file("ZPDCDS_ANNO_MDL", "0002")
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+}
+
+#[actix_web::test]
+pub async fn should_read_vocabulary_file() {
+    let client = start_mock_gateway();
+
+    let vocabularies = client.vocabularies().await.unwrap();
+    assert_eq!(vocabularies.len(), 3);
+    assert_eq!(vocabularies[0].sap_origin, "LOCAL");
+    assert_eq!(vocabularies[0].namespace, "com.sap.vocabularies.UI.v1");
+
+    let raw_xml = client.vocabulary_file(&vocabularies[0]).await.unwrap();
+    assert!(raw_xml.contains("<Term Name=\"LineItem\""), "{}", raw_xml);
+}
+
+#[actix_web::test]
+pub async fn should_call_best_matching_service() {
+    let client = start_mock_gateway();
+
+    let service = client
+        .best_matching_service("ZPDCDS_SRV", 1, 9)
+        .await
+        .unwrap();
+    assert_eq!(service.id, "ZPDCDS_SRV_0001");
+    assert_eq!(service.technical_service_version, 1);
+
+    let err = client
+        .best_matching_service("ZNO_SUCH_SRV", 1, 9)
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+}
+
+#[actix_web::test]
+pub async fn should_clear_metadata_cache_only_if_allowed() {
+    let client = start_mock_gateway();
+
+    let service = client
+        .clear_metadata_cache("ZPDCDS_SRV_0001")
+        .await
+        .unwrap();
+    assert_eq!(service.id, "ZPDCDS_SRV_0001");
+
+    // A Gateway that does not accept POST is asked again with GET
+    assert!(client
+        .clear_metadata_cache("ZGET_ONLY_SRV_0001")
+        .await
+        .is_ok());
+    let err = client
+        .clear_metadata_cache("ZUNKNOWN_SRV_0001")
+        .await
+        .unwrap_err();
+    assert_eq!(err.status(), Some(StatusCode::METHOD_NOT_ALLOWED));
+
+    let client = start_mock_gateway_with(|profile| profile.allow_cache_clear = false);
+    let err = client
+        .clear_metadata_cache("ZPDCDS_SRV_0001")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, CatalogError::NotPermitted(_)), "{}", err);
+}
+
+#[actix_web::test]
+pub async fn should_read_entity_set_feed_of_service() {
+    let client = start_mock_gateway();
+    // Service URLs in the catalog have no trailing slash
+    let service_url = client.service_url().trim_end_matches('/').to_owned();
+    let raw_xml = client
+        .entity_set_feed_at(&service_url, "CatalogCollection", 3)
+        .await
+        .unwrap();
+
+    assert!(
+        raw_xml.contains("<title type=\"text\">CatalogCollection</title>"),
+        "{}",
+        raw_xml
+    );
+}
+
+#[actix_web::test]
+pub async fn should_read_metadata() {
+    let client = start_mock_gateway();
+    let metadata_url = format!("{}$metadata", client.service_url());
+    let raw_xml = client.metadata_at(&metadata_url).await.unwrap();
+
+    assert!(
+        raw_xml.contains("<EntityType Name=\"Service\""),
+        "{}",
+        raw_xml
+    );
+
+    // The session's credentials are never sent to another host, or to another port on the same host
+    let mut other_port = Url::parse(&metadata_url).unwrap();
+    other_port.set_port(Some(1)).unwrap();
+
+    for other_url in [
+        "https://example.com/sap/opu/odata/sap/ZSRV/$metadata",
+        other_port.as_str(),
+    ] {
+        let err = client.metadata_at(other_url).await.unwrap_err();
+        assert!(matches!(err, CatalogError::NotPermitted(_)), "{}", err);
+    }
+}
+
+#[actix_web::test]
+pub async fn should_report_odata_error() {
+    let client = start_mock_gateway();
+    let err = client.services("ZCUSTOM").await.unwrap_err();
+
+    assert_eq!(err.status(), Some(StatusCode::NOT_FOUND));
+    assert_eq!(
+        err.to_string(),
+        "No service found for namespace '', name 'ZCUSTOM_SRV', version '0001' (404 Not Found)"
+    );
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[actix_web::test]
+pub async fn should_follow_next_links() {
+    let client = start_mock_gateway();
+    let query = Query::entity_set("ServiceCollection");
+    let url = client.url(&query);
+
+    let pages: Vec<Page<Service>> = client.pages(&query).map(Result::unwrap).collect().await;
+    assert_eq!(
+        pages.iter().map(|p| p.entries.len()).collect::<Vec<_>>(),
+        vec![25, 25, 10]
+    );
+    assert_eq!(pages[0].next_url, Some(format!("{}?$skiptoken=25", url)));
+    assert_eq!(pages[2].next_url, None);
+
+    let all = client
+        .read_all::<Service>(&query.inline_count())
+        .await
+        .unwrap();
+    assert_eq!(all.entries.len(), 60);
+    assert_eq!(all.total_count, Some(60));
+    assert_eq!(all.entries[2].id, "WDR_ADAPT_UI_SRV_0001");
+}
+
+#[actix_web::test]
+pub async fn should_not_follow_next_links_to_other_systems() {
+    let client = start_mock_gateway();
+    let query = Query::entity_set("ForeignServiceCollection");
+
+    let pages: Vec<Result<Page<Service>, CatalogError>> = client.pages(&query).collect().await;
+    assert_eq!(pages.len(), 2);
+    assert_eq!(pages[0].as_ref().unwrap().entries.len(), 60);
+    assert!(
+        matches!(&pages[1], Err(CatalogError::NotPermitted(msg)) if msg.contains("sapes5.sapdevcenter.com")),
+        "{:?}",
+        pages[1].as_ref().err()
+    );
+}
+
+#[actix_web::test]
+pub async fn should_apply_top_and_skip_across_pages() {
+    let client = start_mock_gateway();
+    let query = Query::entity_set("ServiceCollection").top(30).skip(2);
+    let part = client.read_all::<Service>(&query).await.unwrap();
+
+    assert_eq!(part.entries.len(), 30);
+    assert_eq!(part.entries[0].id, "WDR_ADAPT_UI_SRV_0001");
+    assert_eq!(part.total_count, None);
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_build_service_search_query() {
+    let criteria = ServiceSearch {
+        search: "sales order".to_owned(),
+        title: "O'Brien".to_owned(),
+        technical_service_version: " 2 ".to_owned(),
+        updated_since: "2024-01-31".to_owned(),
+        ..Default::default()
+    };
+    let url = criteria.to_query().unwrap().to_string();
+    let url = percent_encoding::percent_decode_str(&url)
+        .decode_utf8()
+        .unwrap();
+
+    assert!(!criteria.is_empty());
+    assert_eq!(
+        url,
+        "ServiceCollection?$filter=((substringof('O''Brien',Title)) and (TechnicalServiceVersion eq 2)) \
+         and (UpdatedDate ge datetime'2024-01-31T00:00:00')&$orderby=ID asc&search=sales order"
+    );
+
+    assert!(ServiceSearch::default().is_empty());
+
+    let err = ServiceSearch {
+        updated_since: "31.01.2024".to_owned(),
+        ..Default::default()
+    }
+    .to_query()
+    .unwrap_err();
+    assert_eq!(err, "Invalid date '31.01.2024': expected YYYY-MM-DD");
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[actix_web::test]
+pub async fn should_read_json_and_fall_back_to_atom() {
+    let client = start_mock_gateway_with(|profile| profile.format = PayloadFormat::Json);
+
+    let tags = client.tags().await.unwrap();
+    assert_eq!(tags.len(), 8);
+    assert!(client.wants_json());
+
+    // The Gateway rejects JSON for the CatalogCollection, so it is read again as Atom
+    let catalogs = client.catalogs().await.unwrap();
+    assert_eq!(catalogs[0].id, "ES5");
+    assert!(!client.wants_json());
+
+    let tags = client.tags().await.unwrap();
+    assert_eq!(tags.len(), 8);
+}
+
+#[test]
+pub fn should_read_json_error_message() {
+    let err = CatalogError::from_response(
+        StatusCode::BAD_REQUEST,
+        r#"{"error": {"code": "X", "message": {"lang": "en", "value": "Unsupported format"}}}"#,
+    );
+    assert_eq!(err.to_string(), "Unsupported format (400 Bad Request)");
+}
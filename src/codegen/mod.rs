@@ -0,0 +1,160 @@
+use serde::Serialize;
+use std::{env, fs, path::PathBuf, process::Command};
+
+/// Given as the first command line argument, makes this program run `gen_src` instead of doing its usual work.  It is
+/// followed by the service name and schema namespace.
+pub static GENERATOR_ARG: &str = "--generate-odata-bindings";
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// A Rust source file written by `gen_src`
+#[derive(Clone, Debug, Serialize)]
+pub struct GeneratedFile {
+    pub file_name: String,
+    pub source: String,
+}
+
+/// What `parse-sap-odata` generates from one service's metadata: `<name>.rs` for the service document, and
+/// `<name>_metadata.rs` for the metadata document
+#[derive(Clone, Debug, Serialize)]
+pub struct GeneratedBindings {
+    pub module_name: String,
+    pub namespace: String,
+    pub files: Vec<GeneratedFile>,
+    /// Errors reported by the generator, which may have left out one of the files
+    pub messages: Vec<String>,
+}
+
+impl GeneratedBindings {
+    pub fn file(&self, file_name: &str) -> Option<&GeneratedFile> {
+        self.files.iter().find(|file| file.file_name == file_name)
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Runs `parse_sap_odata::parser::gen_src` on metadata that has not been saved in a project
+///
+/// `gen_src` is meant to be called from a build script: it reads `./odata/<name>.xml` relative to the current directory
+/// and writes to `$OUT_DIR`.  Since neither can be changed safely in a running server, the generator runs as a child
+/// process (normally a copy of this program started with [`GENERATOR_ARG`]) inside a temporary workspace that is laid
+/// out the same way.  `rustfmt` must be on the path.
+#[derive(Debug, Clone)]
+pub struct BindingGenerator {
+    exe: PathBuf,
+    work_dir: PathBuf,
+}
+
+impl BindingGenerator {
+    /// A generator that runs the given program with [`GENERATOR_ARG`], creating workspaces in the system's temporary
+    /// directory
+    pub fn new(exe: impl Into<PathBuf>) -> BindingGenerator {
+        BindingGenerator {
+            exe: exe.into(),
+            work_dir: env::temp_dir(),
+        }
+    }
+
+    /// A generator that runs another copy of this program, which must call [`run_generator_if_requested`] at startup
+    pub fn current_exe() -> Result<BindingGenerator, String> {
+        env::current_exe()
+            .map(BindingGenerator::new)
+            .map_err(|err| format!("Unable to find this program's executable: {}", err))
+    }
+
+    /// Generate the Rust modules for a service called `module_name`, whose metadata defines the schema `namespace`
+    pub fn generate(
+        &self,
+        module_name: &str,
+        namespace: &str,
+        raw_xml: &str,
+    ) -> Result<GeneratedBindings, String> {
+        // The workspace gets a random name and must not already exist, so that nobody else can prepare it.  It is
+        // removed when dropped
+        let workspace = tempfile::Builder::new()
+            .prefix("odata-bindings-")
+            .tempdir_in(&self.work_dir)
+            .map_err(|err| format!("Unable to create generator workspace: {}", err))?;
+        let odata_dir = workspace.path().join(crate::project::ODATA_DIR);
+        let out_dir = workspace.path().join("out");
+        let workspace_error =
+            |err: std::io::Error| format!("Unable to prepare generator workspace: {}", err);
+
+        fs::create_dir_all(&odata_dir).map_err(workspace_error)?;
+        fs::create_dir_all(&out_dir).map_err(workspace_error)?;
+        fs::write(odata_dir.join(format!("{}.xml", module_name)), raw_xml)
+            .map_err(workspace_error)?;
+
+        log::info!(
+            "Generating bindings for {} in {}",
+            module_name,
+            workspace.path().display()
+        );
+        let output = Command::new(&self.exe)
+            .args([GENERATOR_ARG, module_name, namespace])
+            .current_dir(workspace.path())
+            .env("OUT_DIR", &out_dir)
+            .output()
+            .map_err(|err| format!("Unable to start {}: {}", self.exe.display(), err))?;
+
+        // gen_src reports errors on stdout, among its instructions to cargo
+        let messages: Vec<String> = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.strip_prefix("Error: "))
+            .map(String::from)
+            .collect();
+
+        if !output.status.success() {
+            return Err(format!(
+                "The generator for {} ended with {}\n{}",
+                module_name,
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        let files = [
+            format!("{}.rs", module_name),
+            format!("{}_metadata.rs", module_name),
+        ]
+        .into_iter()
+        .filter_map(|file_name| {
+            fs::read_to_string(out_dir.join(&file_name))
+                .ok()
+                .map(|source| GeneratedFile { file_name, source })
+        })
+        .collect::<Vec<GeneratedFile>>();
+
+        if files.is_empty() {
+            return Err(if messages.is_empty() {
+                format!("No source code was generated for {}", module_name)
+            } else {
+                messages.join("\n")
+            });
+        }
+
+        Ok(GeneratedBindings {
+            module_name: module_name.to_owned(),
+            namespace: namespace.to_owned(),
+            files,
+            messages,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// If this process was started by a [`BindingGenerator`], run `gen_src` and return `true`.  The caller should then exit
+/// without doing anything else.
+pub fn run_generator_if_requested() -> bool {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    match args.as_slice() {
+        [arg, module_name, namespace] if arg == GENERATOR_ARG => {
+            parse_sap_odata::parser::gen_src(module_name, namespace);
+            true
+        }
+        _ => false,
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
@@ -0,0 +1,82 @@
+use super::*;
+use std::os::unix::fs::PermissionsExt;
+
+static CATALOG_METADATA: &str = "./odata/catalogservice.xml";
+
+/// A generator that stands in for `gen_src` by running a shell script in the workspace
+fn fake_generator(name: &str, script: &str) -> BindingGenerator {
+    let dir = env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    let exe = dir.join("generator.sh");
+
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(&exe, format!("#!/bin/sh\n{}\n", script)).unwrap();
+    fs::set_permissions(&exe, fs::Permissions::from_mode(0o755)).unwrap();
+
+    BindingGenerator { exe, work_dir: dir }
+}
+
+fn workspaces(generator: &BindingGenerator) -> usize {
+    fs::read_dir(&generator.work_dir)
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().path().is_dir())
+        .count()
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_run_generator_in_workspace() {
+    // Like gen_src, read ./odata/<name>.xml and write both modules to $OUT_DIR
+    let generator = fake_generator(
+        "codegen-workspace",
+        r#"[ "$1" = "--generate-odata-bindings" ] || exit 2
+grep -q "Namespace=\"$3\"" "./odata/$2.xml" || exit 3
+echo "pub mod $2 {}" > "$OUT_DIR/$2.rs"
+echo "cargo:rerun-if-changed=./odata/$2.xml"
+echo "Error: rustfmt for metadata document module ended with 1""#,
+    );
+    let raw_xml = fs::read_to_string(CATALOG_METADATA).unwrap();
+
+    let bindings = generator
+        .generate("catalogservice", "catalogservice", &raw_xml)
+        .unwrap();
+
+    assert_eq!(bindings.files.len(), 1);
+    assert_eq!(
+        bindings.file("catalogservice.rs").unwrap().source,
+        "pub mod catalogservice {}\n"
+    );
+    assert!(bindings.file("catalogservice_metadata.rs").is_none());
+    assert_eq!(
+        bindings.messages,
+        ["rustfmt for metadata document module ended with 1"]
+    );
+    assert_eq!(workspaces(&generator), 0);
+
+    fs::remove_dir_all(&generator.work_dir).unwrap();
+}
+
+#[test]
+pub fn should_report_generator_errors() {
+    let generator = fake_generator(
+        "codegen-errors",
+        r#"echo "Error: OData schema for namespace '$3' cannot be found or this is not OData V2 XML""#,
+    );
+
+    let err = generator
+        .generate("catalogservice", "wrong", "<edmx:Edmx/>")
+        .unwrap_err();
+    assert_eq!(
+        err,
+        "OData schema for namespace 'wrong' cannot be found or this is not OData V2 XML"
+    );
+
+    let generator = fake_generator("codegen-panic", "echo 'thread main panicked' >&2; exit 101");
+    let err = generator
+        .generate("catalogservice", "catalogservice", "")
+        .unwrap_err();
+    assert!(err.contains("thread main panicked"), "{}", err);
+    assert_eq!(workspaces(&generator), 0);
+
+    fs::remove_dir_all(&generator.work_dir).unwrap();
+}
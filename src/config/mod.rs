@@ -0,0 +1,1172 @@
+use std::{
+    collections::HashMap,
+    env, fmt, fs,
+    path::{Path, PathBuf},
+};
+
+static DEFAULT_CONFIG_FILE: &str = "config.toml";
+static DOTENV_FILE: &str = ".env";
+static CONFIG_FILE_VARNAME: &str = "SAP_CONFIG_FILE";
+static CONFIG_FILE_FLAG: &str = "--config";
+
+pub static DEFAULT_PROFILE_NAME: &str = "default";
+static DEFAULT_CATALOG_PATH: &str = "/sap/opu/odata/iwfnd/catalogservice;v=2";
+static PROFILES_SECTION: &str = "profiles";
+static PROFILE_VARNAME_PREFIX: &str = "SAP_PROFILE_";
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// A single configuration setting and the name by which it is known in each configuration source
+///
+/// Settings with a `profile_key` belong to the profile called `default`
+struct Setting {
+    env_var: &'static str,
+    toml_key: &'static str,
+    cli_flag: &'static str,
+    default: Option<&'static str>,
+    profile_key: Option<&'static str>,
+}
+
+static HOSTNAME: &str = "SAP_CATALOGSERVICE_HOSTNAME";
+static SERVER_ADDRESS: &str = "SERVER_ADDRESS";
+static SERVER_PORT: &str = "SERVER_PORT";
+static DEFAULT_PROFILE: &str = "SAP_DEFAULT_PROFILE";
+static VOCABULARY_DIR: &str = "SAP_VOCABULARY_DIR";
+static PROJECT_DIR: &str = "SAP_PROJECT_DIR";
+
+static SETTINGS: [Setting; 22] = [
+    Setting {
+        env_var: HOSTNAME,
+        toml_key: "sap.hostname",
+        cli_flag: "--hostname",
+        default: None,
+        profile_key: Some("hostname"),
+    },
+    Setting {
+        env_var: "SAP_PORT",
+        toml_key: "sap.port",
+        cli_flag: "--sap-port",
+        default: None,
+        profile_key: Some("port"),
+    },
+    Setting {
+        env_var: "SAP_CLIENT",
+        toml_key: "sap.client",
+        cli_flag: "--client",
+        default: None,
+        profile_key: Some("client"),
+    },
+    Setting {
+        env_var: "SAP_USER",
+        toml_key: "sap.user",
+        cli_flag: "--user",
+        default: None,
+        profile_key: Some("user"),
+    },
+    Setting {
+        env_var: "SAP_PASSWORD",
+        toml_key: "sap.password",
+        cli_flag: "--password",
+        default: None,
+        profile_key: Some("password"),
+    },
+    Setting {
+        env_var: "SAP_AUTH",
+        toml_key: "sap.auth",
+        cli_flag: "--auth",
+        default: None,
+        profile_key: Some("auth"),
+    },
+    Setting {
+        env_var: "SAP_OAUTH_TOKEN_URL",
+        toml_key: "sap.token_url",
+        cli_flag: "--token-url",
+        default: None,
+        profile_key: Some("token_url"),
+    },
+    Setting {
+        env_var: "SAP_OAUTH_CLIENT_ID",
+        toml_key: "sap.client_id",
+        cli_flag: "--client-id",
+        default: None,
+        profile_key: Some("client_id"),
+    },
+    Setting {
+        env_var: "SAP_OAUTH_CLIENT_SECRET",
+        toml_key: "sap.client_secret",
+        cli_flag: "--client-secret",
+        default: None,
+        profile_key: Some("client_secret"),
+    },
+    Setting {
+        env_var: "SAP_OAUTH_SCOPE",
+        toml_key: "sap.scope",
+        cli_flag: "--scope",
+        default: None,
+        profile_key: Some("scope"),
+    },
+    Setting {
+        env_var: "SAP_CLIENT_CERT",
+        toml_key: "sap.client_cert",
+        cli_flag: "--client-cert",
+        default: None,
+        profile_key: Some("client_cert"),
+    },
+    Setting {
+        env_var: "SAP_CLIENT_KEY",
+        toml_key: "sap.client_key",
+        cli_flag: "--client-key",
+        default: None,
+        profile_key: Some("client_key"),
+    },
+    Setting {
+        env_var: "SAP_CLIENT_CERT_PASSWORD",
+        toml_key: "sap.client_cert_password",
+        cli_flag: "--client-cert-password",
+        default: None,
+        profile_key: Some("client_cert_password"),
+    },
+    Setting {
+        env_var: "SAP_CA_BUNDLE",
+        toml_key: "sap.ca_bundle",
+        cli_flag: "--ca-bundle",
+        default: None,
+        profile_key: Some("ca_bundle"),
+    },
+    Setting {
+        env_var: "SAP_CATALOG_PATH",
+        toml_key: "sap.catalog_path",
+        cli_flag: "--catalog-path",
+        default: None,
+        profile_key: Some("catalog_path"),
+    },
+    Setting {
+        env_var: "SAP_FORMAT",
+        toml_key: "sap.format",
+        cli_flag: "--format",
+        default: None,
+        profile_key: Some("format"),
+    },
+    Setting {
+        env_var: "SAP_ALLOW_CACHE_CLEAR",
+        toml_key: "sap.allow_cache_clear",
+        cli_flag: "--allow-cache-clear",
+        default: None,
+        profile_key: Some("allow_cache_clear"),
+    },
+    Setting {
+        env_var: SERVER_ADDRESS,
+        toml_key: "server.address",
+        cli_flag: "--address",
+        default: Some("0.0.0.0"),
+        profile_key: None,
+    },
+    Setting {
+        env_var: SERVER_PORT,
+        toml_key: "server.port",
+        cli_flag: "--port",
+        default: Some("8080"),
+        profile_key: None,
+    },
+    Setting {
+        env_var: DEFAULT_PROFILE,
+        toml_key: "server.default_profile",
+        cli_flag: "--profile",
+        default: None,
+        profile_key: None,
+    },
+    Setting {
+        env_var: VOCABULARY_DIR,
+        toml_key: "server.vocabulary_dir",
+        cli_flag: "--vocabulary-dir",
+        default: Some("vocabularies"),
+        profile_key: None,
+    },
+    Setting {
+        env_var: PROJECT_DIR,
+        toml_key: "server.project_dir",
+        cli_flag: "--project-dir",
+        default: None,
+        profile_key: None,
+    },
+];
+
+/// The settings that may appear in a `[profiles.<name>]` section
+static PROFILE_KEYS: [&str; 17] = [
+    "hostname",
+    "port",
+    "client",
+    "user",
+    "password",
+    "auth",
+    "token_url",
+    "client_id",
+    "client_secret",
+    "scope",
+    "client_cert",
+    "client_key",
+    "client_cert_password",
+    "ca_bundle",
+    "catalog_path",
+    "format",
+    "allow_cache_clear",
+];
+
+/// The values accepted by the `auth` setting
+static AUTH_BASIC: &str = "basic";
+static AUTH_OAUTH2_CLIENT_CREDENTIALS: &str = "oauth2_client_credentials";
+static AUTH_OAUTH2_PASSWORD: &str = "oauth2_password";
+static AUTH_X509: &str = "x509";
+
+/// The values accepted by the `format` setting
+static FORMAT_ATOM: &str = "atom";
+static FORMAT_JSON: &str = "json";
+
+/// Client certificate files with these extensions are PKCS#12 archives; anything else is treated as PEM
+static PKCS12_EXTENSIONS: [&str; 2] = ["p12", "pfx"];
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Identifies the configuration layer that supplied a value
+#[derive(Clone, Debug, PartialEq)]
+pub enum Source {
+    Default,
+    ConfigFile(PathBuf),
+    DotEnv(usize),
+    Environment,
+    CommandLine,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "the built-in defaults"),
+            Source::ConfigFile(path) => write!(f, "config file {}", path.display()),
+            Source::DotEnv(line) => write!(f, "{} file line {}", DOTENV_FILE, line),
+            Source::Environment => write!(f, "the environment"),
+            Source::CommandLine => write!(f, "the command line"),
+        }
+    }
+}
+
+/// A raw value together with the name it was given and the layer it came from
+#[derive(Clone, Debug)]
+struct RawValue {
+    value: String,
+    name: String,
+    source: Source,
+}
+
+/// The unvalidated values accumulated from all configuration layers
+#[derive(Default)]
+struct RawConfig {
+    settings: HashMap<&'static str, RawValue>,
+    // Profiles are held in the order in which they were first seen
+    profiles: Vec<(String, HashMap<&'static str, RawValue>)>,
+}
+
+impl RawConfig {
+    fn set(&mut self, setting: &Setting, raw_value: RawValue) {
+        match setting.profile_key {
+            Some(key) => self.set_profile_value(DEFAULT_PROFILE_NAME, key, raw_value),
+            None => {
+                self.settings.insert(setting.env_var, raw_value);
+            }
+        }
+    }
+
+    /// Profile names are matched case-insensitively because environment variable names are usually upper case
+    fn set_profile_value(&mut self, profile_name: &str, key: &'static str, raw_value: RawValue) {
+        match self
+            .profiles
+            .iter_mut()
+            .find(|(name, _)| name.eq_ignore_ascii_case(profile_name))
+        {
+            Some((_, values)) => {
+                values.insert(key, raw_value);
+            }
+            None => self
+                .profiles
+                .push((profile_name.to_owned(), HashMap::from([(key, raw_value)]))),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// How to authenticate against an SAP system
+#[derive(Clone)]
+pub enum AuthConfig {
+    Basic {
+        user: String,
+        password: String,
+    },
+    OAuth2 {
+        token_url: String,
+        client_id: String,
+        client_secret: String,
+        scopes: Vec<String>,
+        grant: OAuth2Grant,
+    },
+    ClientCertificate(ClientIdentity),
+}
+
+/// An X.509 client certificate used for mutual TLS
+///
+/// The identity is either a PKCS#12 archive (optionally password protected), or a PEM certificate with a separate
+/// PKCS#8 PEM private key
+#[derive(Clone)]
+pub enum ClientIdentity {
+    Pkcs12 {
+        archive_file: PathBuf,
+        password: Option<String>,
+    },
+    Pem {
+        cert_file: PathBuf,
+        key_file: PathBuf,
+    },
+}
+
+/// The OAuth 2.0 grant type used to obtain an access token
+#[derive(Clone)]
+pub enum OAuth2Grant {
+    ClientCredentials,
+    Password { user: String, password: String },
+}
+
+impl fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthConfig::Basic { user, .. } => f
+                .debug_struct("Basic")
+                .field("user", user)
+                .field("password", &"********")
+                .finish(),
+            AuthConfig::OAuth2 {
+                token_url,
+                client_id,
+                scopes,
+                grant,
+                ..
+            } => f
+                .debug_struct("OAuth2")
+                .field("token_url", token_url)
+                .field("client_id", client_id)
+                .field("client_secret", &"********")
+                .field("scopes", scopes)
+                .field("grant", grant)
+                .finish(),
+            AuthConfig::ClientCertificate(identity) => {
+                f.debug_tuple("ClientCertificate").field(identity).finish()
+            }
+        }
+    }
+}
+
+impl fmt::Debug for ClientIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientIdentity::Pkcs12 { archive_file, .. } => f
+                .debug_struct("Pkcs12")
+                .field("archive_file", archive_file)
+                .field("password", &"********")
+                .finish(),
+            ClientIdentity::Pem {
+                cert_file,
+                key_file,
+            } => f
+                .debug_struct("Pem")
+                .field("cert_file", cert_file)
+                .field("key_file", key_file)
+                .finish(),
+        }
+    }
+}
+
+impl fmt::Debug for OAuth2Grant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OAuth2Grant::ClientCredentials => write!(f, "ClientCredentials"),
+            OAuth2Grant::Password { user, .. } => f
+                .debug_struct("Password")
+                .field("user", user)
+                .field("password", &"********")
+                .finish(),
+        }
+    }
+}
+
+/// The format in which the Gateway is asked to return entities
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PayloadFormat {
+    /// Atom XML, which every Gateway supports
+    #[default]
+    Atom,
+    /// OData V2 verbose JSON (`$format=json`), falling back to Atom if the system rejects it
+    Json,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// The connection details of one SAP system
+#[derive(Clone, Debug)]
+pub struct Profile {
+    pub name: String,
+    pub hostname: String,
+    pub port: Option<u16>,
+    pub client: Option<String>,
+    pub auth: AuthConfig,
+    pub ca_bundle: Option<PathBuf>,
+    pub catalog_path: String,
+    pub format: PayloadFormat,
+    /// Whether users may clear the Gateway's metadata cache for a service.  Off unless explicitly enabled, because
+    /// this affects everyone using the system.
+    pub allow_cache_clear: bool,
+}
+
+impl Profile {
+    /// The scheme, host and optional port of this system
+    pub fn base_url(&self) -> String {
+        match self.port {
+            Some(port) => format!("https://{}:{}", self.hostname, port),
+            None => format!("https://{}", self.hostname),
+        }
+    }
+
+    /// The URL of the CatalogService service document (always ends with a slash)
+    pub fn catalog_service_url(&self) -> String {
+        format!("{}{}/", self.base_url(), self.catalog_path)
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// The validated application configuration
+///
+/// Values are resolved from the following layers, where each layer overrides the one before it:
+///
+/// 1. Built-in defaults
+/// 1. TOML config file (`config.toml`, or the file named by `--config` or `SAP_CONFIG_FILE`)
+/// 1. `.env` file
+/// 1. Process environment
+/// 1. Command line flags
+///
+/// The `sap.*` settings describe the profile called `default`.  Further profiles are described either by
+/// `[profiles.<name>]` sections in the config file, or by `SAP_PROFILE_<NAME>_<SETTING>` variables.
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub profiles: Vec<Profile>,
+    pub default_profile: String,
+    pub server_address: String,
+    pub server_port: u16,
+    /// Where vocabulary files downloaded from the Gateway are stored
+    pub vocabulary_dir: PathBuf,
+    /// The Rust project into whose `odata` directory service metadata is saved.  Without it, metadata cannot be saved.
+    pub project_dir: Option<PathBuf>,
+}
+
+impl Config {
+    /// Build the configuration from the command line, the process environment, the `.env` file and the config file
+    pub fn load() -> Result<Config, String> {
+        let args: Vec<String> = env::args().skip(1).collect();
+        let env_vars: HashMap<String, String> = env::vars().collect();
+
+        let config_file = match config_file_path(&args, &env_vars)? {
+            Some((path, explicit)) => match fs::read_to_string(&path) {
+                Ok(contents) => Some((path, contents)),
+                Err(_) if !explicit => None,
+                Err(err) => {
+                    return Err(format!(
+                        "Unable to read config file {}: {}",
+                        path.display(),
+                        err
+                    ))
+                }
+            },
+            None => None,
+        };
+
+        let dotenv = fs::read_to_string(DOTENV_FILE).ok();
+
+        Config::from_layers(config_file, dotenv.as_deref(), &env_vars, &args)
+    }
+
+    /// Build the configuration from explicitly supplied layers
+    pub fn from_layers(
+        config_file: Option<(PathBuf, String)>,
+        dotenv: Option<&str>,
+        env_vars: &HashMap<String, String>,
+        args: &[String],
+    ) -> Result<Config, String> {
+        let mut raw = RawConfig::default();
+
+        for setting in SETTINGS.iter() {
+            if let Some(default) = setting.default {
+                raw.set(
+                    setting,
+                    RawValue {
+                        value: default.to_owned(),
+                        name: setting.env_var.to_owned(),
+                        source: Source::Default,
+                    },
+                );
+            }
+        }
+
+        if let Some((path, contents)) = config_file {
+            read_toml_layer(&mut raw, &path, &contents)?;
+        }
+
+        if let Some(contents) = dotenv {
+            read_dotenv_layer(&mut raw, contents)?;
+        }
+
+        // Sort the environment so that new profiles are always created in the same order
+        let mut sorted_env: Vec<(&String, &String)> = env_vars.iter().collect();
+        sorted_env.sort();
+
+        for (varname, value) in sorted_env {
+            set_variable(&mut raw, varname, value, Source::Environment);
+        }
+
+        read_cli_layer(&mut raw, args)?;
+
+        Config::validate(&raw)
+    }
+
+    /// Find a profile by name, ignoring case, or the default profile if no name is given
+    pub fn profile(&self, name: Option<&str>) -> Result<&Profile, String> {
+        let name = name.unwrap_or(&self.default_profile);
+
+        self.profiles
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Unknown SAP system profile '{}'", name))
+    }
+
+    // - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+    fn validate(raw: &RawConfig) -> Result<Config, String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        let server_address = match raw.settings.get(SERVER_ADDRESS) {
+            Some(rv) if !rv.value.trim().is_empty() => rv.value.trim().to_owned(),
+            Some(rv) => {
+                errors.push(format!("{} in {} must not be empty", rv.name, rv.source));
+                String::new()
+            }
+            None => String::new(),
+        };
+
+        let server_port = raw
+            .settings
+            .get(SERVER_PORT)
+            .and_then(|rv| validate_port(rv, &mut errors))
+            .unwrap_or_default();
+
+        let vocabulary_dir = match raw.settings.get(VOCABULARY_DIR) {
+            Some(rv) if !rv.value.trim().is_empty() => PathBuf::from(rv.value.trim()),
+            Some(rv) => {
+                errors.push(format!("{} in {} must not be empty", rv.name, rv.source));
+                PathBuf::new()
+            }
+            None => PathBuf::new(),
+        };
+
+        let project_dir = match raw.settings.get(PROJECT_DIR) {
+            Some(rv) if rv.value.trim().is_empty() => {
+                errors.push(format!("{} in {} must not be empty", rv.name, rv.source));
+                None
+            }
+            Some(rv) => Some(PathBuf::from(rv.value.trim())),
+            None => None,
+        };
+
+        let profiles: Vec<Profile> = raw
+            .profiles
+            .iter()
+            .filter_map(|(name, values)| validate_profile(name, values, &mut errors))
+            .collect();
+
+        if raw.profiles.is_empty() {
+            let hostname = SETTINGS
+                .iter()
+                .find(|s| s.env_var == HOSTNAME)
+                .expect("the hostname is a setting");
+            errors.push(format!(
+                "No SAP system configured: set {} in the config file, {} in {} or the environment, or pass {}; \
+                 alternatively, define one or more [{}.<name>] sections in the config file",
+                hostname.toml_key, hostname.env_var, DOTENV_FILE, hostname.cli_flag, PROFILES_SECTION
+            ));
+        }
+
+        // The default profile is known by the name it was first given, whatever case it is requested in
+        let default_profile = match raw.settings.get(DEFAULT_PROFILE) {
+            Some(rv) => {
+                let name = rv.value.trim();
+
+                match raw
+                    .profiles
+                    .iter()
+                    .find(|(p, _)| p.eq_ignore_ascii_case(name))
+                {
+                    Some((p, _)) => p.to_owned(),
+                    None => {
+                        errors.push(format!(
+                            "Invalid value '{}' for {} in {}: no profile with that name has been defined",
+                            rv.value, rv.name, rv.source
+                        ));
+                        name.to_owned()
+                    }
+                }
+            }
+            None => raw
+                .profiles
+                .iter()
+                .map(|(name, _)| name.to_owned())
+                .find(|name| name.eq_ignore_ascii_case(DEFAULT_PROFILE_NAME))
+                .or_else(|| raw.profiles.first().map(|(name, _)| name.to_owned()))
+                .unwrap_or_default(),
+        };
+
+        if errors.is_empty() {
+            Ok(Config {
+                profiles,
+                default_profile,
+                server_address,
+                server_port,
+                vocabulary_dir,
+                project_dir,
+            })
+        } else {
+            Err(errors.join("\n"))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Validation
+// ---------------------------------------------------------------------------------------------------------------------
+fn validate_port(rv: &RawValue, errors: &mut Vec<String>) -> Option<u16> {
+    match rv.value.trim().parse::<u16>() {
+        Ok(port) if port > 0 => Some(port),
+        _ => {
+            errors.push(format!(
+                "Invalid value '{}' for {} in {}: expected a port number between 1 and 65535",
+                rv.value, rv.name, rv.source
+            ));
+            None
+        }
+    }
+}
+
+/// Describe where a missing profile setting could be supplied
+fn missing_profile_value(profile_name: &str, key: &str) -> String {
+    match SETTINGS.iter().find(|s| s.profile_key == Some(key)) {
+        Some(setting) if profile_name.eq_ignore_ascii_case(DEFAULT_PROFILE_NAME) => format!(
+            "No value for {}: set {} in the config file, {} in {} or the environment, or pass {}",
+            setting.env_var, setting.toml_key, setting.env_var, DOTENV_FILE, setting.cli_flag
+        ),
+        _ => format!(
+            "No value for {} in profile {}: set {}.{}.{} in the config file, or {}{}_{} in {} or the environment",
+            key,
+            profile_name,
+            PROFILES_SECTION,
+            profile_name,
+            key,
+            PROFILE_VARNAME_PREFIX,
+            profile_name.to_uppercase(),
+            key.to_uppercase(),
+            DOTENV_FILE
+        ),
+    }
+}
+
+fn validate_profile(
+    name: &str,
+    values: &HashMap<&'static str, RawValue>,
+    errors: &mut Vec<String>,
+) -> Option<Profile> {
+    let error_count = errors.len();
+
+    let mut required = |key: &'static str| match values.get(key) {
+        Some(rv) if rv.value.trim().is_empty() => {
+            errors.push(format!("{} in {} must not be empty", rv.name, rv.source));
+            String::new()
+        }
+        Some(rv) => rv.value.trim().to_owned(),
+        None => {
+            errors.push(missing_profile_value(name, key));
+            String::new()
+        }
+    };
+
+    let hostname = required("hostname");
+
+    let auth_method = values
+        .get("auth")
+        .map(|rv| rv.value.trim().to_lowercase())
+        .unwrap_or_else(|| AUTH_BASIC.to_owned());
+
+    let auth = if auth_method == AUTH_BASIC {
+        Some(AuthConfig::Basic {
+            user: required("user"),
+            password: required("password"),
+        })
+    } else if auth_method == AUTH_OAUTH2_CLIENT_CREDENTIALS || auth_method == AUTH_OAUTH2_PASSWORD {
+        let token_url = required("token_url");
+        let client_id = required("client_id");
+        let client_secret = required("client_secret");
+        let grant = if auth_method == AUTH_OAUTH2_PASSWORD {
+            OAuth2Grant::Password {
+                user: required("user"),
+                password: required("password"),
+            }
+        } else {
+            OAuth2Grant::ClientCredentials
+        };
+
+        Some(AuthConfig::OAuth2 {
+            token_url,
+            client_id,
+            client_secret,
+            scopes: values
+                .get("scope")
+                .map(|rv| {
+                    rv.value
+                        .split([' ', ','])
+                        .filter(|s| !s.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            grant,
+        })
+    } else if auth_method == AUTH_X509 {
+        let cert_file = PathBuf::from(required("client_cert"));
+        let is_pkcs12 = cert_file
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| PKCS12_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+
+        Some(AuthConfig::ClientCertificate(if is_pkcs12 {
+            ClientIdentity::Pkcs12 {
+                archive_file: cert_file,
+                password: values
+                    .get("client_cert_password")
+                    .map(|rv| rv.value.to_owned()),
+            }
+        } else {
+            ClientIdentity::Pem {
+                cert_file,
+                key_file: PathBuf::from(required("client_key")),
+            }
+        }))
+    } else {
+        let rv = &values["auth"];
+        errors.push(format!(
+            "Invalid value '{}' for {} in {}: expected one of {}, {}, {} or {}",
+            rv.value,
+            rv.name,
+            rv.source,
+            AUTH_BASIC,
+            AUTH_OAUTH2_CLIENT_CREDENTIALS,
+            AUTH_OAUTH2_PASSWORD,
+            AUTH_X509
+        ));
+        None
+    };
+
+    if let Some(AuthConfig::OAuth2 { token_url, .. }) = &auth {
+        if !token_url.is_empty()
+            && !token_url.starts_with("https://")
+            && !token_url.starts_with("http://")
+        {
+            let rv = &values["token_url"];
+            errors.push(format!(
+                "Invalid value '{}' for {} in {}: expected an http:// or https:// URL",
+                rv.value, rv.name, rv.source
+            ));
+        }
+    }
+
+    // A hostname is only the host part of a URL; scheme or path components are a common mistake
+    if hostname.contains("://") || hostname.contains('/') || hostname.contains(':') {
+        let rv = &values["hostname"];
+        errors.push(format!(
+            "Invalid value '{}' for {} in {}: expected a hostname such as sapes5.sapdevcenter.com, not a URL",
+            rv.value, rv.name, rv.source
+        ));
+    }
+
+    let port = values.get("port").and_then(|rv| validate_port(rv, errors));
+
+    let client = match values.get("client") {
+        Some(rv) => {
+            let client = rv.value.trim();
+
+            if client.len() != 3 || !client.chars().all(|c| c.is_ascii_digit()) {
+                errors.push(format!(
+                    "Invalid value '{}' for {} in {}: an SAP client must be three digits",
+                    rv.value, rv.name, rv.source
+                ));
+            }
+
+            Some(client.to_owned())
+        }
+        None => None,
+    };
+
+    let catalog_path = match values.get("catalog_path") {
+        Some(rv) => {
+            let path = rv.value.trim().trim_end_matches('/');
+
+            if !path.starts_with('/') {
+                errors.push(format!(
+                    "Invalid value '{}' for {} in {}: the catalog path must start with /",
+                    rv.value, rv.name, rv.source
+                ));
+            }
+
+            path.to_owned()
+        }
+        None => DEFAULT_CATALOG_PATH.to_owned(),
+    };
+
+    let format = match values.get("format") {
+        Some(rv) if rv.value.trim().eq_ignore_ascii_case(FORMAT_ATOM) => PayloadFormat::Atom,
+        Some(rv) if rv.value.trim().eq_ignore_ascii_case(FORMAT_JSON) => PayloadFormat::Json,
+        Some(rv) => {
+            errors.push(format!(
+                "Invalid value '{}' for {} in {}: expected {} or {}",
+                rv.value, rv.name, rv.source, FORMAT_ATOM, FORMAT_JSON
+            ));
+            PayloadFormat::Atom
+        }
+        None => PayloadFormat::Atom,
+    };
+
+    let allow_cache_clear = match values.get("allow_cache_clear") {
+        Some(rv) => match rv.value.trim().to_lowercase().as_str() {
+            "true" | "yes" | "1" => true,
+            "false" | "no" | "0" => false,
+            _ => {
+                errors.push(format!(
+                    "Invalid value '{}' for {} in {}: expected true or false",
+                    rv.value, rv.name, rv.source
+                ));
+                false
+            }
+        },
+        None => false,
+    };
+
+    let auth = auth?;
+    let ca_bundle = values
+        .get("ca_bundle")
+        .map(|rv| PathBuf::from(rv.value.trim()));
+
+    (errors.len() == error_count).then(|| Profile {
+        name: name.to_owned(),
+        hostname,
+        port,
+        client,
+        auth,
+        ca_bundle,
+        catalog_path,
+        format,
+        allow_cache_clear,
+    })
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Configuration layers
+// ---------------------------------------------------------------------------------------------------------------------
+/// Locate the config file.  The boolean is true if the file was named explicitly and therefore must exist
+fn config_file_path(
+    args: &[String],
+    env_vars: &HashMap<String, String>,
+) -> Result<Option<(PathBuf, bool)>, String> {
+    let mut idx = 0;
+
+    while idx < args.len() {
+        if let Some(path) = args[idx].strip_prefix(&format!("{}=", CONFIG_FILE_FLAG)) {
+            return Ok(Some((PathBuf::from(path), true)));
+        }
+
+        if args[idx] == CONFIG_FILE_FLAG {
+            return match args.get(idx + 1) {
+                Some(path) => Ok(Some((PathBuf::from(path), true))),
+                None => Err(format!(
+                    "Command line flag {} requires a value",
+                    CONFIG_FILE_FLAG
+                )),
+            };
+        }
+
+        idx += 1;
+    }
+
+    if let Some(path) = env_vars.get(CONFIG_FILE_VARNAME) {
+        return Ok(Some((PathBuf::from(path), true)));
+    }
+
+    let default_path = PathBuf::from(DEFAULT_CONFIG_FILE);
+    Ok(default_path.exists().then_some((default_path, false)))
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+fn toml_value_to_string(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.to_owned()),
+        toml::Value::Integer(i) => Some(i.to_string()),
+        toml::Value::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+fn read_toml_layer(raw: &mut RawConfig, path: &Path, contents: &str) -> Result<(), String> {
+    let source = Source::ConfigFile(path.to_path_buf());
+    let table = contents
+        .parse::<toml::Table>()
+        .map_err(|err| format!("Unable to parse {}: {}", source, err))?;
+
+    let mut errors: Vec<String> = Vec::new();
+
+    for (section_name, section) in table.iter() {
+        let Some(section_table) = section.as_table() else {
+            errors.push(format!(
+                "Unknown setting '{}' in {}: settings must belong to a [section]",
+                section_name, source
+            ));
+            continue;
+        };
+
+        // Each entry in the profiles section is itself a table
+        if section_name == PROFILES_SECTION {
+            for (profile_name, profile) in section_table.iter() {
+                let Some(profile_table) = profile.as_table() else {
+                    errors.push(format!(
+                        "Invalid value for {}.{} in {}: expected a [{}.{}] section",
+                        PROFILES_SECTION, profile_name, source, PROFILES_SECTION, profile_name
+                    ));
+                    continue;
+                };
+
+                for (key, value) in profile_table.iter() {
+                    let toml_key = format!("{}.{}.{}", PROFILES_SECTION, profile_name, key);
+
+                    let Some(profile_key) = PROFILE_KEYS.iter().find(|k| *k == key) else {
+                        errors.push(format!("Unknown setting '{}' in {}", toml_key, source));
+                        continue;
+                    };
+
+                    match toml_value_to_string(value) {
+                        Some(value) => raw.set_profile_value(
+                            profile_name,
+                            profile_key,
+                            RawValue {
+                                value,
+                                name: toml_key,
+                                source: source.clone(),
+                            },
+                        ),
+                        None => errors.push(format!(
+                            "Invalid value {} for {} in {}: expected a string, a number or a boolean",
+                            value, toml_key, source
+                        )),
+                    }
+                }
+            }
+
+            continue;
+        }
+
+        for (key, value) in section_table.iter() {
+            let toml_key = format!("{}.{}", section_name, key);
+
+            let Some(setting) = SETTINGS.iter().find(|s| s.toml_key == toml_key) else {
+                errors.push(format!("Unknown setting '{}' in {}", toml_key, source));
+                continue;
+            };
+
+            match toml_value_to_string(value) {
+                Some(value) => raw.set(
+                    setting,
+                    RawValue {
+                        value,
+                        name: toml_key,
+                        source: source.clone(),
+                    },
+                ),
+                None => errors.push(format!(
+                    "Invalid value {} for {} in {}: expected a string, a number or a boolean",
+                    value, toml_key, source
+                )),
+            }
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("\n"))
+    }
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// Split a variable name of the form `SAP_PROFILE_<NAME>_<SETTING>` into the profile name and the setting
+///
+/// Where more than one setting matches (`..._CLIENT_CERT_PASSWORD` also ends with `_PASSWORD`), the longest wins
+fn parse_profile_varname(varname: &str) -> Option<(&str, &'static str)> {
+    let rest = varname.strip_prefix(PROFILE_VARNAME_PREFIX)?;
+
+    PROFILE_KEYS
+        .iter()
+        .filter_map(|key| {
+            let suffix = format!("_{}", key.to_uppercase());
+
+            rest.strip_suffix(&suffix)
+                .filter(|name| !name.is_empty())
+                .map(|name| (name, *key))
+        })
+        .max_by_key(|(_, key)| key.len())
+}
+
+/// Apply a variable from either the `.env` file or the environment.  Unknown variable names are ignored.
+fn set_variable(raw: &mut RawConfig, varname: &str, value: &str, source: Source) {
+    let raw_value = RawValue {
+        value: value.to_owned(),
+        name: varname.to_owned(),
+        source,
+    };
+
+    if let Some(setting) = SETTINGS.iter().find(|s| s.env_var == varname) {
+        raw.set(setting, raw_value);
+    } else if let Some((profile_name, key)) = parse_profile_varname(varname) {
+        raw.set_profile_value(profile_name, key, raw_value);
+    }
+}
+
+fn read_dotenv_layer(raw: &mut RawConfig, contents: &str) -> Result<(), String> {
+    for (varname, value, line_no) in parse_dotenv(contents)? {
+        set_variable(raw, &varname, &value, Source::DotEnv(line_no));
+    }
+
+    Ok(())
+}
+
+/// Parse the contents of a `.env` file into `(name, value, line number)` triples
+///
+/// * Blank lines and lines starting with `#` are ignored
+/// * An optional `export ` prefix is permitted
+/// * Values may be enclosed in double quotes (with `\"`, `\\` and `\n` escapes) or single quotes (taken literally)
+/// * Unquoted values end at the first ` #` (inline comment)
+pub fn parse_dotenv(contents: &str) -> Result<Vec<(String, String, usize)>, String> {
+    let mut pairs = Vec::new();
+
+    for (idx, raw_line) in contents.lines().enumerate() {
+        let line_no = idx + 1;
+        let line = raw_line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+
+        let Some((key, value)) = line.split_once('=') else {
+            return Err(format!(
+                "{} file line {}: expected NAME=value but found '{}'",
+                DOTENV_FILE, line_no, raw_line
+            ));
+        };
+
+        let key = key.trim();
+
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!(
+                "{} file line {}: '{}' is not a valid variable name",
+                DOTENV_FILE, line_no, key
+            ));
+        }
+
+        let value = parse_dotenv_value(value.trim())
+            .map_err(|err| format!("{} file line {}: {} for {}", DOTENV_FILE, line_no, err, key))?;
+
+        pairs.push((key.to_owned(), value, line_no));
+    }
+
+    Ok(pairs)
+}
+
+fn parse_dotenv_value(value: &str) -> Result<String, String> {
+    if let Some(rest) = value.strip_prefix('"') {
+        let mut unescaped = String::new();
+        let mut chars = rest.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(unescaped),
+                '\\' => match chars.next() {
+                    Some('n') => unescaped.push('\n'),
+                    Some(other) => unescaped.push(other),
+                    None => break,
+                },
+                _ => unescaped.push(c),
+            }
+        }
+
+        Err("unterminated double quoted value".to_owned())
+    } else if let Some(rest) = value.strip_prefix('\'') {
+        match rest.find('\'') {
+            Some(end) => Ok(rest[..end].to_owned()),
+            None => Err("unterminated single quoted value".to_owned()),
+        }
+    } else {
+        let unquoted = match value.find(" #") {
+            Some(comment_start) => &value[..comment_start],
+            None => value,
+        };
+
+        Ok(unquoted.trim().to_owned())
+    }
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// Read settings from command line flags of the form `--flag value` or `--flag=value`
+fn read_cli_layer(raw: &mut RawConfig, args: &[String]) -> Result<(), String> {
+    let mut args_iter = args.iter();
+
+    while let Some(arg) = args_iter.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_owned())),
+            None => (arg.as_str(), None),
+        };
+
+        let value = match inline_value {
+            Some(value) => value,
+            None => match args_iter.next() {
+                Some(value) => value.to_owned(),
+                None => return Err(format!("Command line flag {} requires a value", flag)),
+            },
+        };
+
+        // The config file location has already been consumed by `config_file_path`
+        if flag == CONFIG_FILE_FLAG {
+            continue;
+        }
+
+        match SETTINGS.iter().find(|s| s.cli_flag == flag) {
+            Some(setting) => raw.set(
+                setting,
+                RawValue {
+                    value,
+                    name: flag.to_owned(),
+                    source: Source::CommandLine,
+                },
+            ),
+            None => return Err(format!("Unknown command line flag '{}'", arg)),
+        }
+    }
+
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
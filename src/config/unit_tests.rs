@@ -0,0 +1,382 @@
+use super::*;
+
+fn args(list: &[&str]) -> Vec<String> {
+    list.iter().map(|a| a.to_string()).collect()
+}
+
+fn env_vars(list: &[(&str, &str)]) -> HashMap<String, String> {
+    list.iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+static DOTENV: &str = "
+# SAP logon details
+SAP_USER=dotenv_user
+SAP_USERNAME=not_me
+export SAP_PASSWORD=\"p@ss # word\"
+SAP_CATALOGSERVICE_HOSTNAME=sapes5.sapdevcenter.com # ES5
+";
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_parse_dotenv_file() {
+    let pairs = parse_dotenv(DOTENV).unwrap();
+
+    assert_eq!(pairs.len(), 4);
+    assert_eq!(
+        pairs[0],
+        ("SAP_USER".to_owned(), "dotenv_user".to_owned(), 3)
+    );
+    assert_eq!(pairs[1].1, "not_me");
+    assert_eq!(pairs[2].1, "p@ss # word");
+    assert_eq!(pairs[3].1, "sapes5.sapdevcenter.com");
+}
+
+#[test]
+pub fn should_reject_malformed_dotenv_line() {
+    let err = parse_dotenv("SAP_USER=me\nSAP_PASSWORD").unwrap_err();
+    assert!(err.contains("line 2"), "{}", err);
+
+    let err = parse_dotenv("SAP_PASSWORD='secret").unwrap_err();
+    assert!(err.contains("unterminated"), "{}", err);
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_not_confuse_similar_variable_names() {
+    let config = Config::from_layers(None, Some(DOTENV), &HashMap::new(), &[]).unwrap();
+    let profile = config.profile(None).unwrap();
+
+    assert!(
+        matches!(&profile.auth, AuthConfig::Basic { user, password } if user == "dotenv_user" && password == "p@ss # word")
+    );
+    assert_eq!(config.server_port, 8080);
+    assert_eq!(config.server_address, "0.0.0.0");
+    assert_eq!(config.vocabulary_dir, PathBuf::from("vocabularies"));
+    assert_eq!(config.project_dir, None);
+}
+
+#[test]
+pub fn should_apply_layers_in_order() {
+    let toml = "
+[sap]
+hostname = \"toml.example.com\"
+user = \"toml_user\"
+password = \"toml_password\"
+
+[server]
+port = 9000
+vocabulary_dir = \"odata/vocabularies\"
+";
+    let config_file = Some((PathBuf::from("config.toml"), toml.to_owned()));
+
+    // Config file alone
+    let config = Config::from_layers(config_file.clone(), None, &HashMap::new(), &[]).unwrap();
+    assert_eq!(config.profile(None).unwrap().hostname, "toml.example.com");
+    assert_eq!(config.server_port, 9000);
+    assert_eq!(config.vocabulary_dir, PathBuf::from("odata/vocabularies"));
+
+    // .env overrides config file, environment overrides .env, command line overrides environment
+    let config = Config::from_layers(
+        config_file,
+        Some(DOTENV),
+        &env_vars(&[("SAP_USER", "env_user"), ("SERVER_PORT", "9001")]),
+        &args(&[
+            "--port",
+            "9002",
+            "--password=cli_password",
+            "--project-dir=../my_app",
+        ]),
+    )
+    .unwrap();
+    let profile = config.profile(None).unwrap();
+
+    assert_eq!(profile.hostname, "sapes5.sapdevcenter.com");
+    assert!(
+        matches!(&profile.auth, AuthConfig::Basic { user, password } if user == "env_user" && password == "cli_password")
+    );
+    assert_eq!(config.server_port, 9002);
+    assert_eq!(config.project_dir, Some(PathBuf::from("../my_app")));
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_name_the_source_of_a_bad_value() {
+    let err = Config::from_layers(
+        None,
+        Some("SERVER_PORT=eighty"),
+        &env_vars(&[
+            ("SAP_USER", "u"),
+            ("SAP_PASSWORD", "p"),
+            ("SAP_CATALOGSERVICE_HOSTNAME", "https://host/path"),
+        ]),
+        &[],
+    )
+    .unwrap_err();
+
+    assert!(
+        err.contains("'eighty' for SERVER_PORT in .env file line 1"),
+        "{}",
+        err
+    );
+    assert!(
+        err.contains("SAP_CATALOGSERVICE_HOSTNAME in the environment"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+pub fn should_report_missing_and_unknown_settings() {
+    let err = Config::from_layers(None, None, &HashMap::new(), &[]).unwrap_err();
+    assert!(err.starts_with("No SAP system configured"), "{}", err);
+    assert!(err.contains("--hostname"), "{}", err);
+
+    let err = Config::from_layers(
+        None,
+        Some("SAP_CATALOGSERVICE_HOSTNAME=host"),
+        &HashMap::new(),
+        &[],
+    )
+    .unwrap_err();
+    assert!(err.contains("No value for SAP_USER"), "{}", err);
+
+    let config_file = Some((PathBuf::from("my.toml"), "[sap]\nusr = \"typo\"".to_owned()));
+    let err = Config::from_layers(config_file, None, &HashMap::new(), &[]).unwrap_err();
+    assert_eq!(err, "Unknown setting 'sap.usr' in config file my.toml");
+
+    let err =
+        Config::from_layers(None, None, &HashMap::new(), &args(&["--verbose", "1"])).unwrap_err();
+    assert_eq!(err, "Unknown command line flag '--verbose'");
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+static PROFILES_TOML: &str = "
+[server]
+default_profile = \"QA\"
+
+[profiles.DEV]
+hostname = \"dev.example.com\"
+port = 44300
+client = \"100\"
+user = \"dev_user\"
+allow_cache_clear = true
+password = \"dev_password\"
+
+[profiles.QA]
+hostname = \"qa.example.com\"
+user = \"qa_user\"
+catalog_path = \"/sap/opu/odata/IWFND/CATALOGSERVICE;v=2/\"
+format = \"JSON\"
+";
+
+#[test]
+pub fn should_read_named_profiles() {
+    let config_file = Some((PathBuf::from("config.toml"), PROFILES_TOML.to_owned()));
+    let config = Config::from_layers(
+        config_file,
+        Some("SAP_PROFILE_QA_PASSWORD=qa_password"),
+        &HashMap::new(),
+        &[],
+    )
+    .unwrap();
+
+    assert_eq!(config.profiles.len(), 2);
+    assert_eq!(config.default_profile, "QA");
+
+    let dev = config.profile(Some("DEV")).unwrap();
+    assert_eq!(dev.base_url(), "https://dev.example.com:44300");
+    assert_eq!(dev.client, Some("100".to_owned()));
+    assert_eq!(dev.format, PayloadFormat::Atom);
+    assert!(dev.allow_cache_clear);
+    assert_eq!(
+        dev.catalog_service_url(),
+        "https://dev.example.com:44300/sap/opu/odata/iwfnd/catalogservice;v=2/"
+    );
+
+    let qa = config.profile(None).unwrap();
+    assert!(matches!(&qa.auth, AuthConfig::Basic { password, .. } if password == "qa_password"));
+    assert_eq!(qa.format, PayloadFormat::Json);
+    assert!(!qa.allow_cache_clear);
+    assert_eq!(
+        qa.catalog_service_url(),
+        "https://qa.example.com/sap/opu/odata/IWFND/CATALOGSERVICE;v=2/"
+    );
+
+    assert_eq!(
+        config.profile(Some("PROD")).unwrap_err(),
+        "Unknown SAP system profile 'PROD'"
+    );
+}
+
+#[test]
+pub fn should_match_profile_names_case_insensitively() {
+    let config_file = Some((
+        PathBuf::from("config.toml"),
+        "[profiles.dev]\nhostname = \"dev.example.com\"\nuser = \"dev_user\"\n".to_owned(),
+    ));
+    let config = Config::from_layers(
+        config_file,
+        None,
+        &env_vars(&[("SAP_PROFILE_DEV_PASSWORD", "dev_password")]),
+        &args(&["--profile", "DEV"]),
+    )
+    .unwrap();
+
+    assert_eq!(config.profiles.len(), 1);
+    assert_eq!(config.default_profile, "dev");
+    assert_eq!(config.profile(Some("Dev")).unwrap().name, "dev");
+    assert!(matches!(
+        &config.profile(None).unwrap().auth,
+        AuthConfig::Basic { password, .. } if password == "dev_password"
+    ));
+}
+
+#[test]
+pub fn should_name_the_profile_of_a_bad_value() {
+    let config_file = Some((PathBuf::from("config.toml"), PROFILES_TOML.to_owned()));
+    let err = Config::from_layers(
+        config_file,
+        None,
+        &env_vars(&[
+            ("SAP_PROFILE_DEV_CLIENT", "1000"),
+            ("SAP_PROFILE_DEV_FORMAT", "xml"),
+        ]),
+        &args(&["--profile", "PROD"]),
+    )
+    .unwrap_err();
+
+    assert!(
+        err.contains("'1000' for SAP_PROFILE_DEV_CLIENT in the environment"),
+        "{}",
+        err
+    );
+    assert!(
+        err.contains("'xml' for SAP_PROFILE_DEV_FORMAT in the environment: expected atom or json"),
+        "{}",
+        err
+    );
+    assert!(
+        err.contains("No value for password in profile QA"),
+        "{}",
+        err
+    );
+    assert!(
+        err.contains("'PROD' for --profile in the command line"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+pub fn should_read_oauth_profile() {
+    let toml = "
+[profiles.BTP]
+hostname = \"my-abap.abap.eu10.hana.ondemand.com\"
+auth = \"oauth2_client_credentials\"
+token_url = \"https://my-subaccount.authentication.eu10.hana.ondemand.com/oauth/token\"
+client_id = \"sb-catalog\"
+scope = \"API_CATALOG, openid\"
+";
+    let config_file = Some((PathBuf::from("config.toml"), toml.to_owned()));
+
+    let err = Config::from_layers(config_file.clone(), None, &HashMap::new(), &[]).unwrap_err();
+    assert_eq!(
+        err,
+        "No value for client_secret in profile BTP: set profiles.BTP.client_secret in the config file, \
+         or SAP_PROFILE_BTP_CLIENT_SECRET in .env or the environment"
+    );
+
+    let config = Config::from_layers(
+        config_file,
+        None,
+        &env_vars(&[("SAP_PROFILE_BTP_CLIENT_SECRET", "s3cr3t")]),
+        &[],
+    )
+    .unwrap();
+
+    match &config.profile(Some("BTP")).unwrap().auth {
+        AuthConfig::OAuth2 {
+            client_id,
+            client_secret,
+            scopes,
+            grant: OAuth2Grant::ClientCredentials,
+            ..
+        } => {
+            assert_eq!(client_id, "sb-catalog");
+            assert_eq!(client_secret, "s3cr3t");
+            assert_eq!(scopes, &vec!["API_CATALOG".to_owned(), "openid".to_owned()]);
+        }
+        other => panic!("Expected client credentials, found {:?}", other),
+    }
+
+    let err = Config::from_layers(
+        None,
+        None,
+        &env_vars(&[
+            ("SAP_CATALOGSERVICE_HOSTNAME", "host"),
+            ("SAP_AUTH", "kerberos"),
+        ]),
+        &[],
+    )
+    .unwrap_err();
+    assert!(
+        err.starts_with("Invalid value 'kerberos' for SAP_AUTH in the environment"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+pub fn should_read_client_certificate_profiles() {
+    let toml = "
+[profiles.PEM]
+hostname = \"pem.example.com\"
+auth = \"x509\"
+client_cert = \"certs/client.crt\"
+client_key = \"certs/client.key\"
+ca_bundle = \"certs/ca.crt\"
+
+[profiles.P12]
+hostname = \"p12.example.com\"
+auth = \"x509\"
+client_cert = \"certs/client.P12\"
+";
+    let config_file = Some((PathBuf::from("config.toml"), toml.to_owned()));
+    let config = Config::from_layers(
+        config_file,
+        None,
+        &env_vars(&[("SAP_PROFILE_P12_CLIENT_CERT_PASSWORD", "changeit")]),
+        &[],
+    )
+    .unwrap();
+
+    let pem = config.profile(Some("PEM")).unwrap();
+    assert_eq!(pem.ca_bundle, Some(PathBuf::from("certs/ca.crt")));
+    assert!(matches!(
+        &pem.auth,
+        AuthConfig::ClientCertificate(ClientIdentity::Pem { key_file, .. }) if key_file == &PathBuf::from("certs/client.key")
+    ));
+
+    // The password must not be mistaken for a Basic authentication password of profile P12_CLIENT_CERT
+    let p12 = config.profile(Some("P12")).unwrap();
+    assert_eq!(p12.ca_bundle, None);
+    assert!(matches!(
+        &p12.auth,
+        AuthConfig::ClientCertificate(ClientIdentity::Pkcs12 { password, .. }) if password.as_deref() == Some("changeit")
+    ));
+
+    let err = Config::from_layers(
+        None,
+        None,
+        &env_vars(&[
+            ("SAP_CATALOGSERVICE_HOSTNAME", "host"),
+            ("SAP_AUTH", "x509"),
+            ("SAP_CLIENT_CERT", "client.pem"),
+        ]),
+        &[],
+    )
+    .unwrap_err();
+    assert!(err.contains("No value for SAP_CLIENT_KEY"), "{}", err);
+}
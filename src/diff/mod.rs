@@ -0,0 +1,417 @@
+use crate::edmx::{
+    Association, AssociationSet, ComplexType, EdmxModel, EntityContainer, EntitySet, EntityType,
+    FunctionImport, NavigationProperty, Parameter, Property, SapAttribute, Schema,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// The kind of metadata element that changed
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Element {
+    Schema,
+    EntityType,
+    ComplexType,
+    Property,
+    NavigationProperty,
+    Association,
+    EntityContainer,
+    EntitySet,
+    AssociationSet,
+    FunctionImport,
+    Parameter,
+}
+
+/// An attribute whose value differs between the two documents.  `None` means the attribute is absent
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct AttributeChange {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// An element that was added, removed or changed
+///
+/// The path is the element's namespace qualified name, as used for its anchor in the service documentation.  Only a
+/// changed element lists attribute changes; the children of an added or removed element are not listed separately.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub element: Element,
+    pub path: String,
+    pub attributes: Vec<AttributeChange>,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// The structural differences between two versions of a service's metadata
+///
+/// Elements are matched by name, schemas by namespace.  Each group of sibling elements (such as the properties of an
+/// entity type) lists the removed and changed elements in the order of the old document, each followed by the changes
+/// to its children, and then the added elements in the order of the new document.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct MetadataDiff {
+    pub added: usize,
+    pub removed: usize,
+    pub changed: usize,
+    pub changes: Vec<Change>,
+}
+
+impl MetadataDiff {
+    pub fn between(old: &EdmxModel, new: &EdmxModel) -> MetadataDiff {
+        let mut changes = Vec::new();
+        diff_elements(&mut changes, "", &old.schemas, &new.schemas);
+
+        let count = |kind| changes.iter().filter(|change| change.kind == kind).count();
+
+        MetadataDiff {
+            added: count(ChangeKind::Added),
+            removed: count(ChangeKind::Removed),
+            changed: count(ChangeKind::Changed),
+            changes,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// The compared attributes of an element, by their name in the metadata document
+type Attributes = BTreeMap<String, String>;
+
+/// A metadata element that can be matched by name and compared attribute by attribute
+trait Compared {
+    const ELEMENT: Element;
+
+    fn name(&self) -> &str;
+    fn attributes(&self) -> Attributes;
+
+    /// Add the changes to the elements this one contains
+    fn diff_children(&self, _new: &Self, _path: &str, _changes: &mut Vec<Change>) {}
+}
+
+fn diff_elements<T: Compared>(changes: &mut Vec<Change>, parent: &str, old: &[T], new: &[T]) {
+    let path_of = |element: &T| match parent {
+        "" => element.name().to_owned(),
+        _ => format!("{}.{}", parent, element.name()),
+    };
+    let change = |kind, path, attributes| Change {
+        kind,
+        element: T::ELEMENT,
+        path,
+        attributes,
+    };
+
+    for old_element in old {
+        let path = path_of(old_element);
+
+        match new.iter().find(|n| n.name() == old_element.name()) {
+            Some(new_element) => {
+                let attributes =
+                    diff_attributes(&old_element.attributes(), &new_element.attributes());
+
+                if !attributes.is_empty() {
+                    changes.push(change(ChangeKind::Changed, path.clone(), attributes));
+                }
+
+                old_element.diff_children(new_element, &path, changes);
+            }
+            None => changes.push(change(ChangeKind::Removed, path, Vec::new())),
+        }
+    }
+
+    for new_element in new
+        .iter()
+        .filter(|n| !old.iter().any(|o| o.name() == n.name()))
+    {
+        changes.push(change(ChangeKind::Added, path_of(new_element), Vec::new()));
+    }
+}
+
+fn diff_attributes(old: &Attributes, new: &Attributes) -> Vec<AttributeChange> {
+    let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+    names.sort();
+    names.dedup();
+
+    names
+        .into_iter()
+        .filter(|name| old.get(*name) != new.get(*name))
+        .map(|name| AttributeChange {
+            name: name.clone(),
+            old: old.get(name).cloned(),
+            new: new.get(name).cloned(),
+        })
+        .collect()
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// Build the attributes of an element from its plain attributes, its label and its other `sap:` attributes
+fn attributes<'a>(
+    plain: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+    label: &Option<String>,
+    sap: &[SapAttribute],
+) -> Attributes {
+    let mut attributes: Attributes = plain
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name.to_owned(), value.to_owned())))
+        .collect();
+
+    if let Some(label) = label {
+        attributes.insert("sap:label".to_owned(), label.clone());
+    }
+
+    for attr in sap {
+        attributes.insert(format!("sap:{}", attr.name), attr.value.clone());
+    }
+
+    attributes
+}
+
+fn flag(value: bool) -> Option<&'static str> {
+    Some(if value { "true" } else { "false" })
+}
+
+impl Compared for Schema {
+    const ELEMENT: Element = Element::Schema;
+
+    fn name(&self) -> &str {
+        &self.namespace
+    }
+
+    fn attributes(&self) -> Attributes {
+        Attributes::new()
+    }
+
+    fn diff_children(&self, new: &Self, path: &str, changes: &mut Vec<Change>) {
+        diff_elements(changes, path, &self.entity_types, &new.entity_types);
+        diff_elements(changes, path, &self.complex_types, &new.complex_types);
+        diff_elements(changes, path, &self.associations, &new.associations);
+        diff_elements(
+            changes,
+            path,
+            &self.entity_containers,
+            &new.entity_containers,
+        );
+    }
+}
+
+impl Compared for EntityType {
+    const ELEMENT: Element = Element::EntityType;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        let keys = self.keys.join(", ");
+        attributes([("Key", Some(keys.as_str()))], &self.label, &self.sap)
+    }
+
+    fn diff_children(&self, new: &Self, path: &str, changes: &mut Vec<Change>) {
+        diff_elements(changes, path, &self.properties, &new.properties);
+        diff_elements(
+            changes,
+            path,
+            &self.navigation_properties,
+            &new.navigation_properties,
+        );
+    }
+}
+
+impl Compared for Property {
+    const ELEMENT: Element = Element::Property;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        attributes(
+            [
+                ("Type", Some(self.edm_type.as_str())),
+                ("Nullable", flag(self.nullable)),
+                ("MaxLength", self.max_length.as_deref()),
+                ("Precision", self.precision.as_deref()),
+                ("Scale", self.scale.as_deref()),
+            ],
+            &self.label,
+            &self.sap,
+        )
+    }
+}
+
+impl Compared for NavigationProperty {
+    const ELEMENT: Element = Element::NavigationProperty;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        attributes(
+            [
+                ("Relationship", Some(self.relationship.as_str())),
+                ("Target", self.target_type.as_deref()),
+                ("Multiplicity", self.multiplicity.as_deref()),
+            ],
+            &None,
+            &[],
+        )
+    }
+}
+
+impl Compared for ComplexType {
+    const ELEMENT: Element = Element::ComplexType;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        attributes([], &self.label, &self.sap)
+    }
+
+    fn diff_children(&self, new: &Self, path: &str, changes: &mut Vec<Change>) {
+        diff_elements(changes, path, &self.properties, &new.properties);
+    }
+}
+
+impl Compared for Association {
+    const ELEMENT: Element = Element::Association;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        let mut attrs = attributes([], &None, &self.sap);
+
+        for end in &self.ends {
+            attrs.insert(
+                format!("End {}", end.role),
+                format!("{} [{}]", end.entity_type, end.multiplicity),
+            );
+        }
+
+        attrs
+    }
+}
+
+impl Compared for EntityContainer {
+    const ELEMENT: Element = Element::EntityContainer;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        attributes(
+            [("IsDefaultEntityContainer", flag(self.is_default))],
+            &None,
+            &[],
+        )
+    }
+
+    fn diff_children(&self, new: &Self, path: &str, changes: &mut Vec<Change>) {
+        diff_elements(changes, path, &self.entity_sets, &new.entity_sets);
+        diff_elements(changes, path, &self.association_sets, &new.association_sets);
+        diff_elements(changes, path, &self.function_imports, &new.function_imports);
+    }
+}
+
+impl Compared for EntitySet {
+    const ELEMENT: Element = Element::EntitySet;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        attributes(
+            [("EntityType", Some(self.entity_type.as_str()))],
+            &self.label,
+            &self.sap,
+        )
+    }
+}
+
+impl Compared for AssociationSet {
+    const ELEMENT: Element = Element::AssociationSet;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        let mut attrs = attributes(
+            [("Association", Some(self.association.as_str()))],
+            &None,
+            &self.sap,
+        );
+
+        for end in &self.ends {
+            attrs.insert(format!("End {}", end.role), end.entity_set.clone());
+        }
+
+        attrs
+    }
+}
+
+impl Compared for FunctionImport {
+    const ELEMENT: Element = Element::FunctionImport;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        attributes(
+            [
+                ("ReturnType", self.return_type.as_deref()),
+                ("EntitySet", self.entity_set.as_deref()),
+                ("HttpMethod", self.http_method.as_deref()),
+            ],
+            &self.label,
+            &self.sap,
+        )
+    }
+
+    fn diff_children(&self, new: &Self, path: &str, changes: &mut Vec<Change>) {
+        diff_elements(changes, path, &self.parameters, &new.parameters);
+    }
+}
+
+impl Compared for Parameter {
+    const ELEMENT: Element = Element::Parameter;
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn attributes(&self) -> Attributes {
+        attributes(
+            [
+                ("Type", Some(self.edm_type.as_str())),
+                ("Mode", self.mode.as_deref()),
+                ("Nullable", flag(self.nullable)),
+                ("MaxLength", self.max_length.as_deref()),
+            ],
+            &self.label,
+            &self.sap,
+        )
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
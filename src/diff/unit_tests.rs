@@ -0,0 +1,104 @@
+use super::*;
+use std::fs;
+
+static CATALOG_METADATA: &str = "./odata/catalogservice.xml";
+
+fn catalog_model(edit: impl Fn(String) -> String) -> EdmxModel {
+    let raw_xml = fs::read_to_string(CATALOG_METADATA).unwrap();
+    EdmxModel::parse(&edit(raw_xml)).unwrap()
+}
+
+fn find<'a>(diff: &'a MetadataDiff, path: &str) -> &'a Change {
+    diff.changes
+        .iter()
+        .find(|change| change.path == path)
+        .unwrap_or_else(|| panic!("No change to {}", path))
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_find_no_changes_in_identical_metadata() {
+    let model = catalog_model(|xml| xml);
+    let diff = MetadataDiff::between(&model, &model.clone());
+
+    assert!(diff.is_empty());
+    assert_eq!((diff.added, diff.removed, diff.changed), (0, 0, 0));
+}
+
+#[test]
+pub fn should_report_changed_facets_and_capabilities() {
+    let old = catalog_model(|xml| xml);
+    let new = catalog_model(|xml| {
+        xml.replacen(
+            r#"<Property Name="ID" Type="Edm.String" Nullable="false" MaxLength="40""#,
+            r#"<Property Name="ID" Type="Edm.String" Nullable="true" MaxLength="50""#,
+            1,
+        )
+        .replacen(
+            r#"EntityType="catalogservice.Tag" sap:creatable="false" "#,
+            r#"EntityType="catalogservice.Tag" "#,
+            1,
+        )
+    });
+    let diff = MetadataDiff::between(&old, &new);
+
+    assert_eq!((diff.added, diff.removed, diff.changed), (0, 0, 2));
+
+    let id = find(&diff, "catalogservice.Service.ID");
+    assert_eq!(id.element, Element::Property);
+    assert_eq!(
+        id.attributes,
+        vec![
+            AttributeChange {
+                name: "MaxLength".to_owned(),
+                old: Some("40".to_owned()),
+                new: Some("50".to_owned())
+            },
+            AttributeChange {
+                name: "Nullable".to_owned(),
+                old: Some("false".to_owned()),
+                new: Some("true".to_owned())
+            },
+        ]
+    );
+
+    let tags = find(
+        &diff,
+        "catalogservice.catalogservice_Entities.TagCollection",
+    );
+    assert_eq!(tags.element, Element::EntitySet);
+    assert_eq!(tags.attributes[0].name, "sap:creatable");
+    assert_eq!(tags.attributes[0].new, None);
+}
+
+#[test]
+pub fn should_report_added_and_removed_elements_once() {
+    let old = catalog_model(|xml| xml);
+    let new = catalog_model(|xml| {
+        xml.replacen(r#"Name="ImageUrl""#, r#"Name="IconUrl""#, 1)
+            .replacen(
+                r#"<EntitySet Name="CatalogCollection" EntityType="catalogservice.Catalog" sap:content-version="2"/>"#,
+                "",
+                1,
+            )
+    });
+    let diff = MetadataDiff::between(&new, &old);
+
+    assert_eq!((diff.added, diff.removed, diff.changed), (2, 1, 0));
+    assert_eq!(
+        find(&diff, "catalogservice.Service.IconUrl").kind,
+        ChangeKind::Removed
+    );
+    assert_eq!(
+        find(&diff, "catalogservice.Service.ImageUrl").kind,
+        ChangeKind::Added
+    );
+    assert_eq!(
+        find(
+            &diff,
+            "catalogservice.catalogservice_Entities.CatalogCollection"
+        )
+        .kind,
+        ChangeKind::Added
+    );
+}
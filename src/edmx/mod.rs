@@ -0,0 +1,474 @@
+use quick_xml::{
+    events::{BytesStart, Event},
+    Reader,
+};
+use serde::Serialize;
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// An attribute in SAP's annotation namespace (`sap:`), without its prefix
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SapAttribute {
+    pub name: String,
+    pub value: String,
+}
+
+/// A property of an entity type or complex type
+///
+/// `sap:label` is kept in `label`; every other `sap:` attribute is kept in `sap`.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Property {
+    pub name: String,
+    pub edm_type: String,
+    pub nullable: bool,
+    pub max_length: Option<String>,
+    pub precision: Option<String>,
+    pub scale: Option<String>,
+    pub is_key: bool,
+    pub label: Option<String>,
+    pub sap: Vec<SapAttribute>,
+}
+
+/// A navigation property, with the entity type and multiplicity at the other end of its association
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct NavigationProperty {
+    pub name: String,
+    pub relationship: String,
+    pub from_role: String,
+    pub to_role: String,
+    pub target_type: Option<String>,
+    pub multiplicity: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EntityType {
+    pub name: String,
+    /// The names of the key properties, in order
+    pub keys: Vec<String>,
+    pub properties: Vec<Property>,
+    pub navigation_properties: Vec<NavigationProperty>,
+    pub label: Option<String>,
+    pub sap: Vec<SapAttribute>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ComplexType {
+    pub name: String,
+    pub properties: Vec<Property>,
+    pub label: Option<String>,
+    pub sap: Vec<SapAttribute>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct AssociationEnd {
+    pub role: String,
+    pub entity_type: String,
+    pub multiplicity: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Association {
+    pub name: String,
+    pub ends: Vec<AssociationEnd>,
+    pub sap: Vec<SapAttribute>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EntitySet {
+    pub name: String,
+    pub entity_type: String,
+    pub label: Option<String>,
+    pub sap: Vec<SapAttribute>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct AssociationSetEnd {
+    pub role: String,
+    pub entity_set: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct AssociationSet {
+    pub name: String,
+    pub association: String,
+    pub ends: Vec<AssociationSetEnd>,
+    pub sap: Vec<SapAttribute>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Parameter {
+    pub name: String,
+    pub edm_type: String,
+    pub mode: Option<String>,
+    pub nullable: bool,
+    pub max_length: Option<String>,
+    pub label: Option<String>,
+    pub sap: Vec<SapAttribute>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct FunctionImport {
+    pub name: String,
+    pub return_type: Option<String>,
+    pub entity_set: Option<String>,
+    pub http_method: Option<String>,
+    pub parameters: Vec<Parameter>,
+    pub label: Option<String>,
+    pub sap: Vec<SapAttribute>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EntityContainer {
+    pub name: String,
+    pub is_default: bool,
+    pub entity_sets: Vec<EntitySet>,
+    pub association_sets: Vec<AssociationSet>,
+    pub function_imports: Vec<FunctionImport>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct Schema {
+    pub namespace: String,
+    pub entity_types: Vec<EntityType>,
+    pub complex_types: Vec<ComplexType>,
+    pub associations: Vec<Association>,
+    pub entity_containers: Vec<EntityContainer>,
+}
+
+impl Schema {
+    pub fn entity_type(&self, name: &str) -> Option<&EntityType> {
+        self.entity_types.iter().find(|et| et.name == name)
+    }
+
+    /// Fill in the target of each navigation property from the association it refers to
+    fn resolve_navigation(&mut self) {
+        let associations = &self.associations;
+
+        for nav in self
+            .entity_types
+            .iter_mut()
+            .flat_map(|et| et.navigation_properties.iter_mut())
+        {
+            let association_name = unqualified(&nav.relationship);
+
+            if let Some(end) = associations
+                .iter()
+                .filter(|assoc| assoc.name == association_name)
+                .flat_map(|assoc| assoc.ends.iter())
+                .find(|end| end.role == nav.to_role)
+            {
+                nav.target_type = Some(end.entity_type.clone());
+                nav.multiplicity = Some(end.multiplicity.clone());
+            }
+        }
+    }
+}
+
+/// A name without the namespace that qualifies it
+fn unqualified(name: &str) -> &str {
+    name.rsplit('.').next().unwrap_or(name)
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// The structure of an OData V2 service, as described by its EDMX metadata document
+///
+/// Vocabulary annotations (`<Annotations>` elements) and `<edmx:Reference>`s are not part of the model.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct EdmxModel {
+    pub version: String,
+    pub data_service_version: Option<String>,
+    pub schemas: Vec<Schema>,
+}
+
+impl EdmxModel {
+    pub fn parse(raw_xml: &str) -> Result<EdmxModel, String> {
+        let mut reader = Reader::from_str(raw_xml);
+        let mut builder = ModelBuilder::default();
+
+        loop {
+            match reader.read_event() {
+                Ok(Event::Start(start)) => builder.start(&start),
+                Ok(Event::Empty(empty)) => {
+                    builder.start(&empty);
+                    builder.end(empty.local_name().as_ref());
+                }
+                Ok(Event::End(end)) => builder.end(end.local_name().as_ref()),
+                Ok(Event::Eof) => break,
+                Ok(_) => {}
+                Err(err) => {
+                    return Err(format!(
+                        "Invalid metadata document at position {}: {}",
+                        reader.error_position(),
+                        err
+                    ))
+                }
+            }
+        }
+
+        let mut model = builder.model;
+
+        if model.schemas.is_empty() {
+            return Err("The metadata document does not contain a schema".to_owned());
+        }
+
+        model
+            .schemas
+            .iter_mut()
+            .for_each(Schema::resolve_navigation);
+        Ok(model)
+    }
+
+    /// Look up an entity type by its namespace qualified name
+    pub fn entity_type(&self, qualified_name: &str) -> Option<&EntityType> {
+        let (namespace, name) = qualified_name.rsplit_once('.')?;
+
+        self.schemas
+            .iter()
+            .find(|schema| schema.namespace == namespace)
+            .and_then(|schema| schema.entity_type(name))
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// The attributes of an element, split into its plain attributes, `sap:label` and the other `sap:` attributes
+struct Attributes {
+    plain: Vec<(String, String)>,
+    label: Option<String>,
+    sap: Vec<SapAttribute>,
+}
+
+impl Attributes {
+    fn of(element: &BytesStart) -> Attributes {
+        let mut attributes = Attributes {
+            plain: Vec::new(),
+            label: None,
+            sap: Vec::new(),
+        };
+
+        for attr in element.attributes().flatten() {
+            let name = String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned();
+            let value = attr
+                .unescape_value()
+                .map(|value| value.into_owned())
+                .unwrap_or_default();
+
+            match attr.key.prefix().map(|prefix| prefix.into_inner()) {
+                Some(b"sap") if name == "label" => attributes.label = Some(value),
+                Some(b"sap") => attributes.sap.push(SapAttribute { name, value }),
+                // Namespace declarations are not attributes of the model
+                Some(b"xmlns") => {}
+                _ if name == "xmlns" => {}
+                _ => attributes.plain.push((name, value)),
+            }
+        }
+
+        attributes
+    }
+
+    fn get(&self, name: &str) -> Option<String> {
+        self.plain
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, value)| value.clone())
+    }
+
+    fn name(&self) -> String {
+        self.get("Name").unwrap_or_default()
+    }
+
+    /// Facets such as `Nullable` default to true when absent
+    fn flag(&self, name: &str, default: bool) -> bool {
+        self.get(name).map_or(default, |value| value == "true")
+    }
+}
+
+/// Assembles the model from the start and end of each element
+#[derive(Default)]
+struct ModelBuilder {
+    model: EdmxModel,
+    entity_type: Option<EntityType>,
+    complex_type: Option<ComplexType>,
+    association: Option<Association>,
+    container: Option<EntityContainer>,
+    association_set: Option<AssociationSet>,
+    function_import: Option<FunctionImport>,
+    in_key: bool,
+}
+
+impl ModelBuilder {
+    fn start(&mut self, element: &BytesStart) {
+        let attrs = Attributes::of(element);
+
+        match element.local_name().as_ref() {
+            b"Edmx" => self.model.version = attrs.get("Version").unwrap_or_default(),
+            b"DataServices" => self.model.data_service_version = attrs.get("DataServiceVersion"),
+            b"Schema" => self.model.schemas.push(Schema {
+                namespace: attrs.get("Namespace").unwrap_or_default(),
+                ..Default::default()
+            }),
+            b"EntityType" => {
+                self.entity_type = Some(EntityType {
+                    name: attrs.name(),
+                    label: attrs.label,
+                    sap: attrs.sap,
+                    ..Default::default()
+                })
+            }
+            b"ComplexType" => {
+                self.complex_type = Some(ComplexType {
+                    name: attrs.name(),
+                    label: attrs.label,
+                    sap: attrs.sap,
+                    ..Default::default()
+                })
+            }
+            b"Key" => self.in_key = true,
+            // Property references also appear in referential constraints, which are not keys
+            b"PropertyRef" if self.in_key => {
+                if let Some(et) = self.entity_type.as_mut() {
+                    et.keys.push(attrs.name());
+                }
+            }
+            b"Property" => {
+                let property = Property {
+                    name: attrs.name(),
+                    edm_type: attrs.get("Type").unwrap_or_default(),
+                    nullable: attrs.flag("Nullable", true),
+                    max_length: attrs.get("MaxLength"),
+                    precision: attrs.get("Precision"),
+                    scale: attrs.get("Scale"),
+                    is_key: false,
+                    label: attrs.label,
+                    sap: attrs.sap,
+                };
+
+                if let Some(et) = self.entity_type.as_mut() {
+                    et.properties.push(property);
+                } else if let Some(ct) = self.complex_type.as_mut() {
+                    ct.properties.push(property);
+                }
+            }
+            b"NavigationProperty" => {
+                if let Some(et) = self.entity_type.as_mut() {
+                    et.navigation_properties.push(NavigationProperty {
+                        name: attrs.name(),
+                        relationship: attrs.get("Relationship").unwrap_or_default(),
+                        from_role: attrs.get("FromRole").unwrap_or_default(),
+                        to_role: attrs.get("ToRole").unwrap_or_default(),
+                        ..Default::default()
+                    });
+                }
+            }
+            b"Association" => {
+                self.association = Some(Association {
+                    name: attrs.name(),
+                    sap: attrs.sap,
+                    ..Default::default()
+                })
+            }
+            b"End" => {
+                if let Some(set) = self.association_set.as_mut() {
+                    set.ends.push(AssociationSetEnd {
+                        role: attrs.get("Role").unwrap_or_default(),
+                        entity_set: attrs.get("EntitySet").unwrap_or_default(),
+                    });
+                } else if let Some(assoc) = self.association.as_mut() {
+                    assoc.ends.push(AssociationEnd {
+                        role: attrs.get("Role").unwrap_or_default(),
+                        entity_type: attrs.get("Type").unwrap_or_default(),
+                        multiplicity: attrs.get("Multiplicity").unwrap_or_default(),
+                    });
+                }
+            }
+            b"EntityContainer" => {
+                self.container = Some(EntityContainer {
+                    name: attrs.name(),
+                    is_default: attrs.flag("IsDefaultEntityContainer", false),
+                    ..Default::default()
+                })
+            }
+            b"EntitySet" => {
+                if let Some(container) = self.container.as_mut() {
+                    container.entity_sets.push(EntitySet {
+                        name: attrs.name(),
+                        entity_type: attrs.get("EntityType").unwrap_or_default(),
+                        label: attrs.label,
+                        sap: attrs.sap,
+                    });
+                }
+            }
+            b"AssociationSet" => {
+                self.association_set = Some(AssociationSet {
+                    name: attrs.name(),
+                    association: attrs.get("Association").unwrap_or_default(),
+                    sap: attrs.sap,
+                    ..Default::default()
+                })
+            }
+            b"FunctionImport" => {
+                self.function_import = Some(FunctionImport {
+                    name: attrs.name(),
+                    return_type: attrs.get("ReturnType"),
+                    entity_set: attrs.get("EntitySet"),
+                    http_method: attrs.get("HttpMethod"),
+                    label: attrs.label,
+                    sap: attrs.sap,
+                    ..Default::default()
+                })
+            }
+            b"Parameter" => {
+                if let Some(function) = self.function_import.as_mut() {
+                    function.parameters.push(Parameter {
+                        name: attrs.name(),
+                        edm_type: attrs.get("Type").unwrap_or_default(),
+                        mode: attrs.get("Mode"),
+                        nullable: attrs.flag("Nullable", true),
+                        max_length: attrs.get("MaxLength"),
+                        label: attrs.label,
+                        sap: attrs.sap,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn end(&mut self, local_name: &[u8]) {
+        let schema = self.model.schemas.last_mut();
+
+        match (local_name, schema) {
+            (b"EntityType", Some(schema)) => {
+                if let Some(mut et) = self.entity_type.take() {
+                    for property in et.properties.iter_mut() {
+                        property.is_key = et.keys.contains(&property.name);
+                    }
+                    schema.entity_types.push(et);
+                }
+            }
+            (b"ComplexType", Some(schema)) => schema.complex_types.extend(self.complex_type.take()),
+            (b"Key", _) => self.in_key = false,
+            (b"Association", Some(schema)) => schema.associations.extend(self.association.take()),
+            (b"AssociationSet", _) => {
+                if let (Some(set), Some(container)) =
+                    (self.association_set.take(), self.container.as_mut())
+                {
+                    container.association_sets.push(set);
+                }
+            }
+            (b"FunctionImport", _) => {
+                if let (Some(function), Some(container)) =
+                    (self.function_import.take(), self.container.as_mut())
+                {
+                    container.function_imports.push(function);
+                }
+            }
+            (b"EntityContainer", Some(schema)) => {
+                schema.entity_containers.extend(self.container.take())
+            }
+            _ => {}
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
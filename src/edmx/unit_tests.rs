@@ -0,0 +1,131 @@
+use super::*;
+use std::fs;
+
+static CATALOG_METADATA: &str = "./odata/catalogservice.xml";
+
+static FLIGHT_METADATA: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<edmx:Edmx Version="1.0" xmlns:edmx="http://schemas.microsoft.com/ado/2007/06/edmx" xmlns:m="http://schemas.microsoft.com/ado/2007/08/dataservices/metadata" xmlns:sap="http://www.sap.com/Protocols/SAPData">
+  <edmx:DataServices m:DataServiceVersion="2.0">
+    <Schema Namespace="ZFLIGHT_SRV" xmlns="http://schemas.microsoft.com/ado/2008/09/edm">
+      <EntityType Name="Flight" sap:content-version="1">
+        <Key><PropertyRef Name="Carrid"/><PropertyRef Name="Connid"/></Key>
+        <Property Name="Carrid" Type="Edm.String" Nullable="false" MaxLength="3" sap:label="Airline"/>
+        <Property Name="Connid" Type="Edm.String" Nullable="false" MaxLength="4"/>
+        <Property Name="Price" Type="Edm.Decimal" Precision="15" Scale="2" sap:unit="Currency"/>
+        <Property Name="Route" Type="ZFLIGHT_SRV.Route"/>
+      </EntityType>
+      <ComplexType Name="Route">
+        <Property Name="From" Type="Edm.String" MaxLength="3"/>
+        <Property Name="To" Type="Edm.String" MaxLength="3"/>
+      </ComplexType>
+      <FunctionImport Name="Stray"/>
+      <EntityContainer Name="ZFLIGHT_SRV_Entities" m:IsDefaultEntityContainer="true">
+        <EntitySet Name="Flights" EntityType="ZFLIGHT_SRV.Flight" sap:label="Flights" sap:pageable="false"/>
+        <FunctionImport Name="CheckAvailability" ReturnType="Edm.Boolean" m:HttpMethod="GET" sap:action-for="ZFLIGHT_SRV.Flight">
+          <Parameter Name="Carrid" Type="Edm.String" Mode="In" MaxLength="3"/>
+          <Parameter Name="Seats" Type="Edm.Int32" Mode="In" Nullable="false"/>
+        </FunctionImport>
+      </EntityContainer>
+    </Schema>
+  </edmx:DataServices>
+</edmx:Edmx>"#;
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_parse_catalog_service_metadata() {
+    let raw_xml = fs::read_to_string(CATALOG_METADATA).unwrap();
+    let model = EdmxModel::parse(&raw_xml).unwrap();
+
+    assert_eq!(model.version, "1.0");
+    assert_eq!(model.data_service_version.as_deref(), Some("2.0"));
+    assert_eq!(model.schemas.len(), 1);
+
+    let schema = &model.schemas[0];
+    assert_eq!(schema.namespace, "catalogservice");
+    assert_eq!(schema.associations.len(), 4);
+
+    let service = model.entity_type("catalogservice.Service").unwrap();
+    assert_eq!(service.keys, vec!["ID"]);
+    assert!(
+        service
+            .properties
+            .iter()
+            .find(|p| p.name == "ID")
+            .unwrap()
+            .is_key
+    );
+
+    let version = service
+        .properties
+        .iter()
+        .find(|p| p.name == "TechnicalServiceVersion")
+        .unwrap();
+    assert_eq!(version.edm_type, "Edm.Int16");
+    assert!(!version.nullable);
+    assert_eq!(version.label.as_deref(), Some("Technical Service Version"));
+    assert_eq!(
+        version.sap,
+        vec![SapAttribute {
+            name: "creatable".to_owned(),
+            value: "false".to_owned()
+        }]
+    );
+
+    let annotations = service
+        .navigation_properties
+        .iter()
+        .find(|nav| nav.name == "Annotations")
+        .unwrap();
+    assert_eq!(
+        annotations.target_type.as_deref(),
+        Some("catalogservice.Annotation")
+    );
+
+    let container = &schema.entity_containers[0];
+    assert!(container.is_default);
+    assert!(container
+        .entity_sets
+        .iter()
+        .any(|set| set.name == "ServiceCollection"));
+    assert!(!container.association_sets.is_empty());
+    assert!(container
+        .association_sets
+        .iter()
+        .all(|set| set.ends.len() == 2));
+}
+
+#[test]
+pub fn should_parse_complex_types_and_function_imports() {
+    let model = EdmxModel::parse(FLIGHT_METADATA).unwrap();
+    let schema = &model.schemas[0];
+
+    let flight = schema.entity_type("Flight").unwrap();
+    assert_eq!(flight.keys, vec!["Carrid", "Connid"]);
+    let price = &flight.properties[2];
+    assert_eq!(price.precision.as_deref(), Some("15"));
+    assert_eq!(price.scale.as_deref(), Some("2"));
+    assert!(price.nullable);
+    assert_eq!(price.sap[0].name, "unit");
+
+    assert_eq!(schema.complex_types.len(), 1);
+    assert_eq!(schema.complex_types[0].properties.len(), 2);
+
+    // A function import outside an entity container is not part of the model
+    let container = &schema.entity_containers[0];
+    assert_eq!(container.function_imports.len(), 1);
+
+    let check = &container.function_imports[0];
+    assert_eq!(check.return_type.as_deref(), Some("Edm.Boolean"));
+    assert_eq!(check.http_method.as_deref(), Some("GET"));
+    assert_eq!(check.sap[0].name, "action-for");
+    assert_eq!(check.parameters.len(), 2);
+    assert!(!check.parameters[1].nullable);
+
+    assert_eq!(container.entity_sets[0].label.as_deref(), Some("Flights"));
+}
+
+#[test]
+pub fn should_reject_invalid_metadata() {
+    assert!(EdmxModel::parse("<edmx:Edmx><Schema Namespace=\"X\"></edmx:Edmx>").is_err());
+    assert!(EdmxModel::parse("<edmx:Edmx Version=\"1.0\"/>").is_err());
+}
@@ -0,0 +1,159 @@
+use crate::{
+    catalogservice::{Annotation, EntitySet, Service, Tag},
+    client::{entry_properties, CatalogError},
+};
+use parse_sap_atom_feed::atom::feed::entry::Entry;
+use quick_xml::{events::Event, Reader};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::collections::HashMap;
+
+/// The navigation properties of `Service` that are expanded by [`CatalogClient::expanded_services`]
+///
+/// [`CatalogClient::expanded_services`]: crate::CatalogClient::expanded_services
+pub static SERVICE_NAVIGATION: [&str; 3] = ["EntitySets", "TagCollection", "Annotations"];
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// An entity read with `$expand`, together with the inline feeds of its expanded navigation properties
+///
+/// The Atom feed parser skips the `<m:inline>` content of an entry's navigation links, so the inline feeds are kept as
+/// XML and deserialized only when asked for.
+#[derive(Debug)]
+pub struct Expanded<T> {
+    pub entity: T,
+    inline_feeds: HashMap<String, String>,
+}
+
+impl<T> Expanded<T> {
+    pub(crate) fn new(entity: T, inline_feeds: HashMap<String, String>) -> Expanded<T> {
+        Expanded {
+            entity,
+            inline_feeds,
+        }
+    }
+
+    pub fn is_expanded(&self, navigation_property: &str) -> bool {
+        self.inline_feeds.contains_key(navigation_property)
+    }
+
+    /// The entities reached through an expanded navigation property, or `None` if it was not expanded
+    pub fn navigation<U>(&self, navigation_property: &str) -> Result<Option<Vec<U>>, CatalogError>
+    where
+        U: DeserializeOwned,
+    {
+        match self.inline_feeds.get(navigation_property) {
+            Some(inline_xml) => parse_inline(inline_xml).map(Some).map_err(|err| {
+                CatalogError::Parse(format!(
+                    "Invalid inline content of navigation property {}: {}",
+                    navigation_property, err
+                ))
+            }),
+            None => Ok(None),
+        }
+    }
+}
+
+/// The `<feed>` inside an `<m:inline>` element.  Unlike a top-level feed, only its entries are of interest.
+#[derive(Debug, Deserialize)]
+struct InlineFeed<T> {
+    #[serde(rename = "entry", default = "Vec::new")]
+    entries: Vec<Entry<T>>,
+}
+
+/// A to-many navigation property is expanded to a feed, a to-one property to a single entry or to nothing at all
+fn parse_inline<T>(inline_xml: &str) -> Result<Vec<T>, quick_xml::DeError>
+where
+    T: DeserializeOwned,
+{
+    let entries = if inline_xml.is_empty() {
+        Vec::new()
+    } else if inline_xml.starts_with("<entry") {
+        vec![quick_xml::de::from_str::<Entry<T>>(inline_xml)?]
+    } else {
+        quick_xml::de::from_str::<InlineFeed<T>>(inline_xml)?.entries
+    };
+
+    Ok(entries.into_iter().filter_map(entry_properties).collect())
+}
+
+/// For each top-level entry of a feed (or for a single entry document), the content of every `<m:inline>` element,
+/// keyed by the title of the navigation link that contains it
+pub(crate) fn inline_feeds(raw_xml: &str) -> Result<Vec<HashMap<String, String>>, String> {
+    let mut reader = Reader::from_str(raw_xml);
+    let mut entries: Vec<HashMap<String, String>> = Vec::new();
+    let mut link_title: Option<String> = None;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(start)) => match start.local_name().as_ref() {
+                b"entry" => entries.push(HashMap::new()),
+                b"link" => link_title = attribute(&start, "title"),
+                // Reading the inline content as text skips its nested entries
+                b"inline" => {
+                    let end = start.to_end().into_owned();
+                    let inline_xml = reader
+                        .read_text(end.name())
+                        .map_err(|err| format!("Invalid inline content: {}", err))?;
+
+                    if let (Some(title), Some(entry)) = (link_title.take(), entries.last_mut()) {
+                        entry.insert(title, inline_xml.trim().to_owned());
+                    }
+                }
+                _ => {}
+            },
+            Ok(Event::Empty(empty)) if empty.local_name().as_ref() == b"inline" => {
+                if let (Some(title), Some(entry)) = (link_title.take(), entries.last_mut()) {
+                    entry.insert(title, String::new());
+                }
+            }
+            Ok(Event::End(end)) if end.local_name().as_ref() == b"link" => link_title = None,
+            Ok(Event::Eof) => break,
+            Ok(_) => {}
+            Err(err) => {
+                return Err(format!(
+                    "Invalid XML at position {}: {}",
+                    reader.error_position(),
+                    err
+                ))
+            }
+        }
+    }
+
+    Ok(entries)
+}
+
+fn attribute(start: &quick_xml::events::BytesStart, name: &str) -> Option<String> {
+    start
+        .try_get_attribute(name)
+        .ok()
+        .flatten()
+        .and_then(|attr| attr.unescape_value().ok())
+        .map(|value| value.into_owned())
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// A service together with its entity sets, tags and annotation files.  A navigation property that was not expanded is
+/// `None`.
+#[derive(Debug)]
+pub struct ExpandedService {
+    pub service: Service,
+    pub entity_sets: Option<Vec<EntitySet>>,
+    pub tags: Option<Vec<Tag>>,
+    pub annotations: Option<Vec<Annotation>>,
+}
+
+impl TryFrom<Expanded<Service>> for ExpandedService {
+    type Error = CatalogError;
+
+    fn try_from(expanded: Expanded<Service>) -> Result<Self, Self::Error> {
+        Ok(ExpandedService {
+            entity_sets: expanded.navigation("EntitySets")?,
+            tags: expanded.navigation("TagCollection")?,
+            annotations: expanded.navigation("Annotations")?,
+            service: expanded.entity,
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
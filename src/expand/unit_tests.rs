@@ -0,0 +1,67 @@
+use super::*;
+use parse_sap_atom_feed::atom::feed::Feed;
+use std::{fs, str::FromStr};
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_find_inline_feeds_of_each_entry() {
+    let raw_xml = fs::read_to_string("./test_data/ServiceCollectionExpanded.xml").unwrap();
+    let feeds = inline_feeds(&raw_xml).unwrap();
+
+    // Entries nested inside the inline feeds are not counted as entries of the outer feed
+    assert_eq!(feeds.len(), 2);
+    assert_eq!(feeds[0].len(), 3);
+    assert!(feeds[0]["EntitySets"].starts_with("<feed"));
+
+    let feed = Feed::<Service>::from_str(&raw_xml).unwrap();
+    let services: Vec<ExpandedService> = feed
+        .entries
+        .unwrap()
+        .into_iter()
+        .zip(feeds)
+        .map(|(entry, inline_feeds)| {
+            ExpandedService::try_from(Expanded::new(
+                entry_properties(entry).unwrap(),
+                inline_feeds,
+            ))
+            .unwrap()
+        })
+        .collect();
+
+    let product_data = &services[0];
+    assert_eq!(product_data.service.id, "ZPDCDS_SRV_0001");
+
+    let entity_sets = product_data.entity_sets.as_ref().unwrap();
+    assert_eq!(entity_sets.len(), 2);
+    assert_eq!(entity_sets[1].id, "Suppliers");
+    assert_eq!(entity_sets[1].srv_identifier, "ZPDCDS_SRV_0001");
+    assert_eq!(product_data.tags.as_ref().unwrap().len(), 0);
+    assert_eq!(
+        product_data.annotations.as_ref().unwrap()[0].technical_name,
+        "ZPDCDS_ANNO_MDL"
+    );
+
+    let po_approval = &services[1];
+    assert_eq!(po_approval.entity_sets.as_ref().unwrap().len(), 0);
+    assert_eq!(po_approval.tags.as_ref().unwrap()[0].occurrence, 1);
+}
+
+#[test]
+pub fn should_distinguish_unexpanded_and_empty_navigation() {
+    let raw_xml = "<entry><link title=\"Service\" href=\"x\" rel=\"related\"><m:inline/></link>\
+                   <link title=\"Other\" href=\"y\" rel=\"related\"/></entry>";
+    let feeds = inline_feeds(raw_xml).unwrap();
+    let expanded = Expanded::new((), feeds.into_iter().next().unwrap());
+
+    assert!(expanded.is_expanded("Service"));
+    assert_eq!(
+        expanded
+            .navigation::<Service>("Service")
+            .unwrap()
+            .unwrap()
+            .len(),
+        0
+    );
+    assert!(!expanded.is_expanded("Other"));
+    assert!(expanded.navigation::<Service>("Other").unwrap().is_none());
+}
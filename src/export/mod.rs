@@ -0,0 +1,296 @@
+use crate::{
+    catalogservice::Service,
+    client::{CatalogClient, CatalogError},
+    probe::DEFAULT_CONCURRENCY,
+    project::{metadata_file_name, schema_namespace, ODATA_DIR},
+};
+use chrono::{NaiveDateTime, Utc};
+use futures_util::{stream, StreamExt};
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{Seek, Write},
+    path::PathBuf,
+    time::Duration,
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+/// The name of the manifest written alongside the exported metadata
+pub static MANIFEST_FILE: &str = "manifest.json";
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// How hard to try when exporting metadata
+#[derive(Clone, Debug)]
+pub struct ExportOptions {
+    /// The number of metadata documents requested at the same time
+    pub concurrency: usize,
+    /// How many times a request that failed for a transient reason is repeated
+    pub retries: u32,
+    /// The wait before the first retry, doubled for each one after that
+    pub retry_delay: Duration,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            concurrency: DEFAULT_CONCURRENCY,
+            retries: 2,
+            retry_delay: Duration::from_millis(500),
+        }
+    }
+}
+
+/// A service whose metadata was exported
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportedService {
+    pub service_id: String,
+    pub technical_service_name: String,
+    pub technical_service_version: i16,
+    /// The name given to `gen_src`, which is also the name of the metadata file without `.xml`
+    pub file_name: String,
+    pub namespace: Option<String>,
+    pub bytes: usize,
+    pub attempts: u32,
+}
+
+/// A service whose metadata could not be exported
+#[derive(Clone, Debug, Serialize)]
+pub struct FailedExport {
+    pub service_id: String,
+    pub metadata_url: String,
+    pub http_status: Option<u16>,
+    pub message: String,
+    pub attempts: u32,
+}
+
+/// What an export contains, and what it should have contained but does not
+#[derive(Clone, Debug, Serialize)]
+pub struct ExportManifest {
+    pub catalog: Option<String>,
+    pub started_at: NaiveDateTime,
+    pub finished_at: NaiveDateTime,
+    pub exported: Vec<ExportedService>,
+    pub failed: Vec<FailedExport>,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Where exported metadata is written
+///
+/// Metadata files are stored as `odata/<file name>.xml`, which is where a build script calling
+/// `parse_sap_odata::parser::gen_src` expects to find them, and the manifest as `manifest.json`.
+pub trait ExportSink {
+    fn store_metadata(&mut self, file_name: &str, raw_xml: &str) -> Result<(), String>;
+    fn store_manifest(&mut self, manifest: &ExportManifest) -> Result<(), String>;
+}
+
+/// Writes an export into a directory.  Existing files of the same name are replaced.
+#[derive(Debug, Clone)]
+pub struct DirSink {
+    dir: PathBuf,
+}
+
+impl DirSink {
+    pub fn new(dir: impl Into<PathBuf>) -> DirSink {
+        DirSink { dir: dir.into() }
+    }
+
+    fn write(&self, path: PathBuf, content: &[u8]) -> Result<(), String> {
+        let write_error =
+            |err: std::io::Error| format!("Unable to write {}: {}", path.display(), err);
+
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(write_error)?;
+        }
+        fs::write(&path, content).map_err(write_error)
+    }
+}
+
+impl ExportSink for DirSink {
+    fn store_metadata(&mut self, file_name: &str, raw_xml: &str) -> Result<(), String> {
+        self.write(
+            self.dir.join(ODATA_DIR).join(format!("{}.xml", file_name)),
+            raw_xml.as_bytes(),
+        )
+    }
+
+    fn store_manifest(&mut self, manifest: &ExportManifest) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(manifest).map_err(|err| err.to_string())?;
+        self.write(self.dir.join(MANIFEST_FILE), &json)
+    }
+}
+
+/// Writes an export as a zip archive
+pub struct ZipSink<W: Write + Seek> {
+    zip: ZipWriter<W>,
+}
+
+impl<W: Write + Seek> ZipSink<W> {
+    pub fn new(writer: W) -> ZipSink<W> {
+        ZipSink {
+            zip: ZipWriter::new(writer),
+        }
+    }
+
+    /// Write the archive's central directory and return the underlying writer
+    pub fn finish(self) -> Result<W, String> {
+        self.zip
+            .finish()
+            .map_err(|err| format!("Unable to finish zip archive: {}", err))
+    }
+
+    fn write(&mut self, name: String, content: &[u8]) -> Result<(), String> {
+        let zip_error = |err: String| format!("Unable to add {} to zip archive: {}", name, err);
+
+        self.zip
+            .start_file(name.as_str(), SimpleFileOptions::default())
+            .map_err(|err| zip_error(err.to_string()))?;
+        self.zip
+            .write_all(content)
+            .map_err(|err| zip_error(err.to_string()))
+    }
+}
+
+impl<W: Write + Seek> ExportSink for ZipSink<W> {
+    fn store_metadata(&mut self, file_name: &str, raw_xml: &str) -> Result<(), String> {
+        self.write(
+            format!("{}/{}.xml", ODATA_DIR, file_name),
+            raw_xml.as_bytes(),
+        )
+    }
+
+    fn store_manifest(&mut self, manifest: &ExportManifest) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(manifest).map_err(|err| err.to_string())?;
+        self.write(MANIFEST_FILE.to_owned(), &json)
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// A failure is worth retrying if the Gateway could not be reached, or said it was unable to respond just now
+fn is_transient(err: &CatalogError) -> bool {
+    match err {
+        CatalogError::Request(_) => true,
+        CatalogError::Status { status, .. } => {
+            *status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+        }
+        _ => false,
+    }
+}
+
+/// Read a service's metadata, retrying transient failures.  Also returns the number of attempts made.
+async fn fetch_metadata(
+    client: &CatalogClient,
+    service: &Service,
+    options: &ExportOptions,
+) -> (Result<String, CatalogError>, u32) {
+    let mut attempts = 0;
+    let mut delay = options.retry_delay;
+
+    loop {
+        attempts += 1;
+
+        match client.metadata(service).await {
+            Err(err) if attempts <= options.retries && is_transient(&err) => {
+                log::warn!(
+                    "Attempt {} to read the metadata of {} failed: {}",
+                    attempts,
+                    service.id,
+                    err
+                );
+                actix_rt::time::sleep(delay).await;
+                delay *= 2;
+            }
+            outcome => return (outcome, attempts),
+        }
+    }
+}
+
+/// Export the metadata of every service into the sink, then add a manifest listing which services were exported and
+/// which failed
+///
+/// No more than `options.concurrency` metadata documents are requested at once, and they are stored in the order of
+/// the services, so that when two services share a file name, the first of them is always the one exported.  A
+/// service that cannot be read does not stop the export; only an error writing to the sink does.
+pub async fn export_metadata<S: ExportSink>(
+    client: &CatalogClient,
+    services: &[Service],
+    catalog: Option<&str>,
+    options: &ExportOptions,
+    sink: &mut S,
+) -> Result<ExportManifest, String> {
+    let started_at = Utc::now().naive_utc();
+
+    // The same service may have been selected more than once
+    let mut seen: HashSet<&str> = HashSet::new();
+    let services: Vec<&Service> = services
+        .iter()
+        .filter(|service| seen.insert(&service.id))
+        .collect();
+
+    let mut downloads = stream::iter(services)
+        .map(|service| async move { (service, fetch_metadata(client, service, options).await) })
+        .buffered(options.concurrency.max(1));
+
+    let mut file_names: HashMap<String, String> = HashMap::new();
+    let mut exported: Vec<ExportedService> = Vec::new();
+    let mut failed: Vec<FailedExport> = Vec::new();
+
+    while let Some((service, (outcome, attempts))) = downloads.next().await {
+        let file_name = metadata_file_name(
+            &service.technical_service_name,
+            service.technical_service_version,
+        );
+        let failure = |http_status: Option<u16>, message: String| FailedExport {
+            service_id: service.id.clone(),
+            metadata_url: service.metadata_url.clone(),
+            http_status,
+            message,
+            attempts,
+        };
+
+        match outcome {
+            Ok(raw_xml) => match file_names.get(&file_name) {
+                Some(other_id) => failed.push(failure(
+                    None,
+                    format!(
+                        "Service {} has already been exported as {}",
+                        other_id, file_name
+                    ),
+                )),
+                None => {
+                    sink.store_metadata(&file_name, &raw_xml)?;
+                    file_names.insert(file_name.clone(), service.id.clone());
+                    exported.push(ExportedService {
+                        service_id: service.id.clone(),
+                        technical_service_name: service.technical_service_name.clone(),
+                        technical_service_version: service.technical_service_version,
+                        file_name,
+                        namespace: schema_namespace(&raw_xml),
+                        bytes: raw_xml.len(),
+                        attempts,
+                    });
+                }
+            },
+            Err(err) => failed.push(failure(err.status().map(|s| s.as_u16()), err.to_string())),
+        }
+    }
+
+    exported.sort_by(|a, b| a.service_id.cmp(&b.service_id));
+    failed.sort_by(|a, b| a.service_id.cmp(&b.service_id));
+
+    let manifest = ExportManifest {
+        catalog: catalog.map(String::from),
+        started_at,
+        finished_at: Utc::now().naive_utc(),
+        exported,
+        failed,
+    };
+    sink.store_manifest(&manifest)?;
+
+    Ok(manifest)
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
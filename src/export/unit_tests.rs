@@ -0,0 +1,208 @@
+use super::*;
+use crate::config::{AuthConfig, PayloadFormat, Profile};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use std::{
+    io::{Cursor, Read},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+use zip::ZipArchive;
+
+/// Start a Gateway whose services respond according to their names, and a client for it
+///
+/// `FLAKY_SRV` is unavailable the first time its metadata is requested, and `SLOW_SRV` takes a while to respond.  The number of metadata requests is counted.
+fn start_mock_gateway() -> (String, CatalogClient, Arc<AtomicUsize>) {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let app_requests = requests.clone();
+    let flaky_requests = Arc::new(AtomicUsize::new(0));
+
+    let server = HttpServer::new(move || {
+        let requests = app_requests.clone();
+        let flaky_requests = flaky_requests.clone();
+
+        App::new().default_service(web::to(move |req: HttpRequest| {
+            requests.fetch_add(1, Ordering::SeqCst);
+            let flaky_requests = flaky_requests.clone();
+
+            async move {
+                let metadata = || {
+                    HttpResponse::Ok()
+                        .content_type("application/xml")
+                        .body(fs::read("./odata/catalogservice.xml").unwrap())
+                };
+
+                match req.path() {
+                    path if path.starts_with("/sap/opu/odata/sap/OK_SRV") => metadata(),
+                    path if path.starts_with("/sap/opu/odata/sap/SLOW_SRV") => {
+                        actix_rt::time::sleep(Duration::from_millis(50)).await;
+                        metadata()
+                    }
+                    path if path.starts_with("/sap/opu/odata/sap/FLAKY_SRV") => {
+                        match flaky_requests.fetch_add(1, Ordering::SeqCst) {
+                            0 => HttpResponse::ServiceUnavailable().finish(),
+                            _ => metadata(),
+                        }
+                    }
+                    _ => HttpResponse::NotFound()
+                        .content_type("application/xml")
+                        .body(fs::read("./test_data/ODataError.xml").unwrap()),
+                }
+            }
+        }))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+
+    let base_url = format!("http://{}", server.addrs()[0]);
+    actix_rt::spawn(server.run());
+
+    let client = CatalogClient::new(&Profile {
+        name: "TEST".to_owned(),
+        hostname: "127.0.0.1".to_owned(),
+        port: None,
+        client: None,
+        auth: AuthConfig::Basic {
+            user: "user".to_owned(),
+            password: "secret".to_owned(),
+        },
+        ca_bundle: None,
+        catalog_path: "/sap/opu/odata/iwfnd/catalogservice;v=2".to_owned(),
+        format: PayloadFormat::Atom,
+        allow_cache_clear: false,
+    })
+    .unwrap()
+    .with_service_url(format!(
+        "{}/sap/opu/odata/iwfnd/catalogservice;v=2/",
+        base_url
+    ));
+
+    (base_url, client, requests)
+}
+
+fn service(base_url: &str, name: &str) -> Service {
+    Service {
+        id: format!("Z{}_0001", name),
+        technical_service_name: name.to_owned(),
+        technical_service_version: 1,
+        metadata_url: format!("{}/sap/opu/odata/sap/{}/$metadata", base_url, name),
+        ..Default::default()
+    }
+}
+
+fn quick_retries() -> ExportOptions {
+    ExportOptions {
+        retry_delay: Duration::from_millis(10),
+        ..Default::default()
+    }
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[actix_web::test]
+pub async fn should_export_catalog_to_zip_with_manifest() {
+    let (base_url, client, requests) = start_mock_gateway();
+    let services: Vec<Service> = ["OK_SRV", "FLAKY_SRV", "MISSING_SRV", "OK_SRV"]
+        .iter()
+        .map(|name| service(&base_url, name))
+        .collect();
+
+    let mut sink = ZipSink::new(Cursor::new(Vec::new()));
+    let manifest = export_metadata(&client, &services, Some("ES5"), &quick_retries(), &mut sink)
+        .await
+        .unwrap();
+
+    // The duplicate is read once, the transient failure is retried, and the missing service is not
+    assert_eq!(requests.load(Ordering::SeqCst), 4);
+    assert_eq!(manifest.catalog.as_deref(), Some("ES5"));
+
+    let exported: Vec<(&str, &str, u32)> = manifest
+        .exported
+        .iter()
+        .map(|e| (e.service_id.as_str(), e.file_name.as_str(), e.attempts))
+        .collect();
+    assert_eq!(
+        exported,
+        [
+            ("ZFLAKY_SRV_0001", "flaky_srv", 2),
+            ("ZOK_SRV_0001", "ok_srv", 1)
+        ]
+    );
+    assert_eq!(
+        manifest.exported[0].namespace.as_deref(),
+        Some("catalogservice")
+    );
+
+    assert_eq!(manifest.failed.len(), 1);
+    assert_eq!(manifest.failed[0].service_id, "ZMISSING_SRV_0001");
+    assert_eq!(manifest.failed[0].http_status, Some(404));
+    assert_eq!(manifest.failed[0].attempts, 1);
+
+    let mut archive = ZipArchive::new(sink.finish().unwrap()).unwrap();
+    let mut names: Vec<&str> = archive.file_names().collect();
+    names.sort();
+    assert_eq!(
+        names,
+        ["manifest.json", "odata/flaky_srv.xml", "odata/ok_srv.xml"]
+    );
+
+    let mut manifest_json = String::new();
+    archive
+        .by_name(MANIFEST_FILE)
+        .unwrap()
+        .read_to_string(&mut manifest_json)
+        .unwrap();
+    assert!(manifest_json.contains("\"service_id\": \"ZMISSING_SRV_0001\""));
+}
+
+#[actix_web::test]
+pub async fn should_keep_first_requested_service_when_file_names_collide() {
+    let (base_url, client, _) = start_mock_gateway();
+
+    // Both services are stored as ok_srv.xml, and the first one to be requested responds last
+    let mut slow = service(&base_url, "SLOW_SRV");
+    slow.technical_service_name = "OK_SRV".to_owned();
+    let services = [slow, service(&base_url, "OK_SRV")];
+
+    let manifest = export_metadata(
+        &client,
+        &services,
+        None,
+        &quick_retries(),
+        &mut ZipSink::new(Cursor::new(Vec::new())),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(manifest.exported.len(), 1);
+    assert_eq!(manifest.exported[0].service_id, "ZSLOW_SRV_0001");
+    assert_eq!(manifest.failed.len(), 1);
+    assert_eq!(manifest.failed[0].service_id, "ZOK_SRV_0001");
+}
+
+#[actix_web::test]
+pub async fn should_export_to_directory() {
+    let (base_url, client, _) = start_mock_gateway();
+    let dir = std::env::temp_dir().join(format!("export-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let manifest = export_metadata(
+        &client,
+        &[service(&base_url, "OK_SRV")],
+        None,
+        &quick_retries(),
+        &mut DirSink::new(&dir),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(manifest.exported.len(), 1);
+    assert_eq!(
+        fs::read_to_string(dir.join("odata/ok_srv.xml")).unwrap(),
+        fs::read_to_string("./odata/catalogservice.xml").unwrap()
+    );
+    assert!(dir.join(MANIFEST_FILE).exists());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
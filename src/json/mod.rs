@@ -0,0 +1,109 @@
+use crate::client::{CatalogError, Page};
+use chrono::{DateTime, NaiveDateTime};
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+static DATE_PREFIX: &str = "/Date(";
+static DATE_SUFFIX: &str = ")/";
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Read one page of a collection in OData V2 verbose JSON
+///
+/// The entries are found in `d.results` (or directly in `d` for services that still use the V1 format), the total count
+/// in `d.__count` and the URL of the next page in `d.__next`.
+pub(crate) fn read_page<T>(page_url: &str, raw_json: &str) -> Result<Page<T>, CatalogError>
+where
+    T: DeserializeOwned,
+{
+    let invalid =
+        |err: String| CatalogError::Parse(format!("Invalid JSON feed {}: {}", page_url, err));
+    let data = data(raw_json).map_err(invalid)?;
+
+    let (results, total_count, next_url) = match data {
+        Value::Array(results) => (results, None, None),
+        Value::Object(mut data) => (
+            match data.remove("results") {
+                Some(Value::Array(results)) => results,
+                _ => return Err(invalid("d.results is not an array".to_owned())),
+            },
+            // The count is an Edm.Int64, which V2 JSON represents as a string
+            data.get("__count")
+                .and_then(|count| count.as_str())
+                .and_then(|count| count.parse::<usize>().ok()),
+            data.get("__next")
+                .and_then(|next| next.as_str())
+                .map(String::from),
+        ),
+        _ => return Err(invalid("d is neither an object nor an array".to_owned())),
+    };
+
+    let entries = results
+        .into_iter()
+        .map(|entry| serde_json::from_value::<T>(normalize_dates(entry)))
+        .collect::<Result<Vec<T>, serde_json::Error>>()
+        .map_err(|err| invalid(err.to_string()))?;
+
+    Ok(Page {
+        entries,
+        total_count,
+        next_url,
+    })
+}
+
+/// Read a single entity in OData V2 verbose JSON
+pub(crate) fn read_entry<T>(entry_url: &str, raw_json: &str) -> Result<T, CatalogError>
+where
+    T: DeserializeOwned,
+{
+    data(raw_json)
+        .and_then(|entry| {
+            serde_json::from_value::<T>(normalize_dates(entry)).map_err(|err| err.to_string())
+        })
+        .map_err(|err| CatalogError::Parse(format!("Invalid JSON entry {}: {}", entry_url, err)))
+}
+
+/// Every V2 JSON response wraps its payload in an object called `d`
+fn data(raw_json: &str) -> Result<Value, String> {
+    match serde_json::from_str::<Value>(raw_json).map_err(|err| err.to_string())? {
+        Value::Object(mut response) => response
+            .remove("d")
+            .ok_or_else(|| "missing d object".to_owned()),
+        _ => Err("expected a JSON object".to_owned()),
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Parse an Edm.DateTime in V2 JSON format, `/Date(<milliseconds since 1970-01-01T00:00:00Z>)/`
+///
+/// A trailing `+hhmm` or `-hhmm` offset (used for Edm.DateTimeOffset) is ignored, since the milliseconds are already UTC.
+pub fn parse_json_date(value: &str) -> Option<NaiveDateTime> {
+    let millis = value.strip_prefix(DATE_PREFIX)?.strip_suffix(DATE_SUFFIX)?;
+    let millis = match millis.get(1..)?.find(['+', '-']) {
+        Some(offset_idx) => &millis[..offset_idx + 1],
+        None => millis,
+    };
+
+    DateTime::from_timestamp_millis(millis.parse::<i64>().ok()?).map(|dt| dt.naive_utc())
+}
+
+/// The generated types expect dates in the ISO 8601 format used by Atom XML
+fn normalize_dates(value: Value) -> Value {
+    match value {
+        Value::String(text) => match parse_json_date(&text) {
+            Some(date) => Value::String(date.format("%Y-%m-%dT%H:%M:%S%.f").to_string()),
+            None => Value::String(text),
+        },
+        Value::Object(object) => Value::Object(
+            object
+                .into_iter()
+                .map(|(name, value)| (name, normalize_dates(value)))
+                .collect(),
+        ),
+        Value::Array(array) => Value::Array(array.into_iter().map(normalize_dates).collect()),
+        other => other,
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
@@ -0,0 +1,74 @@
+use super::*;
+use crate::catalogservice::{Service, Tag};
+use chrono::NaiveDate;
+use std::fs;
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_parse_json_dates() {
+    let expected = NaiveDate::from_ymd_opt(2021, 1, 22)
+        .unwrap()
+        .and_hms_opt(2, 11, 49)
+        .unwrap();
+
+    assert_eq!(parse_json_date("/Date(1611281509000)/"), Some(expected));
+    assert_eq!(
+        parse_json_date("/Date(1611281509000+0060)/"),
+        Some(expected)
+    );
+    assert_eq!(
+        parse_json_date("/Date(-86400000)/"),
+        NaiveDate::from_ymd_opt(1969, 12, 31)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+    );
+    assert_eq!(parse_json_date("/Date()/"), None);
+    assert_eq!(parse_json_date("2021-01-22T02:11:49"), None);
+}
+
+#[test]
+pub fn should_read_json_feed() {
+    let raw_json = fs::read_to_string("./test_data/ServiceCollection.json").unwrap();
+    let page = read_page::<Service>("https://host/ServiceCollection", &raw_json).unwrap();
+
+    assert_eq!(page.entries.len(), 2);
+    assert_eq!(page.total_count, Some(60));
+    assert!(page
+        .next_url
+        .unwrap()
+        .ends_with("$skiptoken=2&$format=json"));
+
+    let srv = &page.entries[0];
+    assert_eq!(srv.id, "ZSEPMRA_GR_POST_0001");
+    assert_eq!(srv.description, "EPM Reference App \"Goods Receipt Post\"");
+    assert_eq!(srv.technical_service_version, 1);
+    assert!(srv.is_sap_service);
+    assert_eq!(
+        srv.updated_date.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        "2021-01-22T02:11:49"
+    );
+}
+
+#[test]
+pub fn should_read_json_entry_and_v1_collection() {
+    let tag = read_entry::<Tag>(
+        "https://host/TagCollection('X')",
+        r#"{"d": {"__metadata": {"type": "catalogservice.Tag"}, "ID": "X", "Text": "x", "Occurrence": 3}}"#,
+    )
+    .unwrap();
+    assert_eq!(tag.occurrence, 3);
+
+    let page = read_page::<Tag>(
+        "https://host/TagCollection",
+        r#"{"d": [{"ID": "X", "Text": "x", "Occurrence": 3}]}"#,
+    )
+    .unwrap();
+    assert_eq!(page.entries.len(), 1);
+    assert_eq!(page.next_url, None);
+
+    let err = read_page::<Tag>("https://host/TagCollection", r#"{"results": []}"#).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Invalid JSON feed https://host/TagCollection: missing d object"
+    );
+}
@@ -0,0 +1,38 @@
+//! Read the OData services published by an SAP Gateway's catalog service
+//!
+//! [`CatalogClient`] reads catalogs, services, entity sets, tags, annotations, vocabularies and service metadata from
+//! one SAP system described by a [`config::Profile`].  Entries are returned as the types generated from the catalog
+//! service's own metadata (`odata/catalogservice.xml`).
+pub mod auth;
+pub mod client;
+pub mod codegen;
+pub mod config;
+pub mod diff;
+pub mod edmx;
+pub mod expand;
+pub mod export;
+pub mod json;
+pub mod probe;
+pub mod project;
+pub mod query;
+pub mod scaffold;
+pub mod session;
+pub mod vocabulary;
+
+// The generated source does not pass every clippy lint
+#[allow(clippy::needless_borrow, clippy::unnecessary_mut_passed)]
+pub mod generated {
+    include!(concat!(env!("OUT_DIR"), "/catalogservice.rs"));
+}
+
+pub use client::{CatalogClient, CatalogError, EntityList, Page, ServiceSearch};
+pub use expand::{Expanded, ExpandedService};
+pub use export::{DirSink, ExportManifest, ExportOptions, ZipSink};
+pub use generated::catalogservice;
+pub use probe::{ProbeResult, ProbeStatus};
+pub use query::{Filter, Literal, Order, Query};
+pub use vocabulary::VocabularyStore;
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
@@ -1,81 +1,56 @@
-pub mod auth;
 pub mod err_handlers;
+pub mod ui_session;
 
-use crate::{auth::fetch_auth, err_handlers::error_handlers};
-
-use actix_web::{
-    error, get, http::StatusCode, middleware, web, App, Error, HttpResponse, HttpServer, Result,
+use crate::{
+    err_handlers::error_handlers,
+    ui_session::{
+        AnnotationUsage, BestMatch, BestMatchForm, ServiceDetails, TagRow, UiSession,
+        UiSessionStore, UiState, VocabularyRow,
+    },
 };
-use anyhow::anyhow;
-use parse_sap_atom_feed::{
-    atom::{feed::Feed, AtomService},
-    odata_error::ODataError,
+use read_sap_odata_catalog::{
+    catalogservice::{Service, Vocabulary},
+    codegen::{run_generator_if_requested, BindingGenerator},
+    config::Config,
+    diff::MetadataDiff,
+    edmx::EdmxModel,
+    export::{export_metadata, DirSink, ExportOptions, ZipSink, MANIFEST_FILE},
+    probe::{probe_services, DEFAULT_CONCURRENCY},
+    project::{metadata_file_name, schema_namespace, ProjectDir, SaveOutcome},
+    scaffold::{ProjectScaffold, ScaffoldService},
+    vocabulary::safe_file_name,
+    CatalogClient, CatalogError, ProbeStatus, ServiceSearch, VocabularyStore,
 };
-use serde::{de::DeserializeOwned, Deserialize, Serialize};
-use serde_json::json;
-use std::{
-    collections::HashMap,
-    fs::File,
-    io::BufReader,
-    io::{self, BufRead},
-    path::Path,
-    str::{self, FromStr},
-    sync::Mutex,
+
+use actix_web::{
+    error, get, http::StatusCode, middleware, post, web, App, Error, HttpRequest, HttpResponse,
+    HttpServer, Result,
 };
+use futures_util::{stream, StreamExt, TryStreamExt};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{collections::HashMap, io::Cursor, sync::Arc};
 use tinytemplate::TinyTemplate;
 
-include!(concat!(env!("OUT_DIR"), "/catalogservice.rs"));
-
-use catalogservice::*;
-
 static INDEX: &str = include_str!("../html/index.html");
-static CATALOGSERVICE_VARNAME: &[u8] = "SAP_CATALOGSERVICE_HOSTNAME".as_bytes();
-static HOST_PATH: &[u8] = "/sap/opu/odata/iwfnd".as_bytes();
-static SERVICE_NAME: &[u8] = "catalogservice;v=2".as_bytes();
-
-// ---------------------------------------------------------------------------------------------------------------------
-pub fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
-where
-    P: AsRef<Path>,
-{
-    let file = File::open(filename)?;
-    Ok(BufReader::new(file).lines())
-}
-
-// ---------------------------------------------------------------------------------------------------------------------
-fn fetch_env_var(varname: &str) -> Result<String, String> {
-    let mut value = String::from("unknown");
-
-    // Try to obtain the environment variable file .env
-    if let Ok(lines) = read_lines(".env") {
-        for line in lines {
-            match line {
-                Ok(l) => {
-                    if l.starts_with(varname) {
-                        let (_, u) = l.split_at(l.find("=").unwrap() + 1);
-                        value = u.to_owned();
-                    }
-                }
-                Err(_) => (),
-            }
-        }
-    }
+static SERVICE: &str = include_str!("../html/service.html");
+static BINDINGS: &str = include_str!("../html/bindings.html");
+static DOCS: &str = include_str!("../html/docs.html");
+static DIFF: &str = include_str!("../html/diff.html");
 
-    if value.eq("unknown") {
-        Err(format!("No value for {} found in .env file", varname))
-    } else {
-        Ok(value)
-    }
-}
+/// A search shows no more than this many matching services
+static MAX_SEARCH_RESULTS: usize = 500;
 
 // ---------------------------------------------------------------------------------------------------------------------
-#[derive(Serialize, Debug)]
+#[derive(Debug)]
 struct AppState {
-    hostname: String,
-    catalog_list: Mutex<Option<Vec<String>>>,
-    service_list: Mutex<Option<Vec<(String, String)>>>,
-    error_msg: Mutex<Option<String>>,
-    last_srv: Mutex<Option<String>>,
+    config: Config,
+    clients: HashMap<String, Arc<CatalogClient>>,
+    ui_sessions: UiSessionStore,
+    vocabularies: VocabularyStore,
+    project: Option<ProjectDir>,
+    generator: BindingGenerator,
 }
 
 // ---------------------------------------------------------------------------------------------------------------------
@@ -83,32 +58,78 @@ struct AppState {
 // ---------------------------------------------------------------------------------------------------------------------
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+    // This program is also started to run the Rust binding generator in a temporary workspace
+    if run_generator_if_requested() {
+        return Ok(());
+    }
+
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
-    let hostname = match fetch_env_var(str::from_utf8(CATALOGSERVICE_VARNAME).unwrap()) {
-        Ok(value) => value,
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(err_msg) => {
+            log::error!("{err_msg}");
+            std::process::exit(0x01);
+        }
+    };
+
+    let bind_address = (config.server_address.clone(), config.server_port);
+
+    for profile in config.profiles.iter() {
+        log::info!(
+            "SAP system profile {} = {}",
+            profile.name,
+            profile.catalog_service_url()
+        );
+    }
+
+    log::info!(
+        "Starting HTTP server at http://{}:{}",
+        bind_address.0,
+        bind_address.1
+    );
+
+    // Client certificates and CA bundles are read now so that a bad file is reported at startup
+    let clients = match config
+        .profiles
+        .iter()
+        .map(|p| CatalogClient::new(p).map(|client| (p.name.clone(), Arc::new(client))))
+        .collect::<Result<HashMap<_, _>, String>>()
+    {
+        Ok(clients) => clients,
         Err(err_msg) => {
             log::error!("{err_msg}");
             std::process::exit(0x01);
         }
     };
 
-    log::info!("SAP CatalogService hostname = {}", hostname);
-    log::info!("Starting HTTP server at http://localhost:8080");
+    let generator = match BindingGenerator::current_exe() {
+        Ok(generator) => generator,
+        Err(err_msg) => {
+            log::error!("{err_msg}");
+            std::process::exit(0x01);
+        }
+    };
 
     // Initial app state
     let app_state = web::Data::new(AppState {
-        hostname: hostname,
-        catalog_list: Mutex::new(None),
-        service_list: Mutex::new(None),
-        error_msg: Mutex::new(None),
-        last_srv: Mutex::new(None),
+        vocabularies: VocabularyStore::new(&config.vocabulary_dir),
+        project: config.project_dir.as_ref().map(ProjectDir::new),
+        generator,
+        config,
+        clients,
+        ui_sessions: UiSessionStore::default(),
     });
 
     HttpServer::new(move || {
         let mut tt = TinyTemplate::<'_>::new();
 
         tt.add_template("index.html", INDEX).unwrap();
+        tt.add_template("service.html", SERVICE).unwrap();
+        tt.add_template("bindings.html", BINDINGS).unwrap();
+        tt.add_template("docs.html", DOCS).unwrap();
+        tt.add_template("diff.html", DIFF).unwrap();
+        tt.add_formatter("url_encode", url_encode);
 
         App::new()
             .app_data(app_state.clone())
@@ -116,10 +137,30 @@ async fn main() -> std::io::Result<()> {
             .wrap(middleware::Logger::default())
             .service(web::resource("/").route(web::get().to(doc_root)))
             .service(catalog_services)
+            .service(search_services)
+            .service(best_matching_service)
             .service(fetch_metadata)
+            .service(save_metadata)
+            .service(confirm_cache_clear)
+            .service(clear_metadata_cache)
+            .service(annotations)
+            .service(annotation_services)
+            .service(annotation_file)
+            .service(show_vocabularies)
+            .service(vocabulary_file)
+            .service(store_vocabularies)
+            .service(show_tags)
+            .service(tag_services)
+            .service(service_page)
+            .service(probe_catalog)
+            .service(export_catalog_metadata)
+            .service(service_bindings)
+            .service(service_docs)
+            .service(compare_metadata)
+            .service(scaffold_project)
             .service(web::scope("").wrap(error_handlers()))
     })
-    .bind(("0.0.0.0", 8080))?
+    .bind(bind_address)?
     .run()
     .await
 }
@@ -128,96 +169,114 @@ async fn main() -> std::io::Result<()> {
 // Serve document root
 // ---------------------------------------------------------------------------------------------------------------------
 async fn doc_root(
+    req: HttpRequest,
     app_state: web::Data<AppState>,
     tmpl: web::Data<TinyTemplate<'_>>,
-    _query: web::Query<HashMap<String, String>>,
+    query: web::Query<HashMap<String, String>>,
 ) -> Result<HttpResponse, Error> {
     log::info!("---> doc_root()");
-    let srv_doc_url = format!(
-        "https://{}/{}/{}/",
-        app_state.hostname,
-        str::from_utf8(HOST_PATH).unwrap(),
-        str::from_utf8(SERVICE_NAME).unwrap()
-    );
 
-    *app_state.service_list.lock().unwrap() = None;
-    *app_state.error_msg.lock().unwrap() = None;
-    *app_state.last_srv.lock().unwrap() = None;
+    let mut ui = app_state.ui_sessions.load(&req);
+    ui.state.service_list = None;
+    ui.state.service_details = None;
+    ui.state.service_count = None;
+    ui.state.annotation_list = None;
+    ui.state.annotation_usage = None;
+    ui.state.vocabulary_list = None;
+    ui.state.tag_list = None;
+    ui.state.probe_results = None;
+    ui.state.error_msg = None;
+    ui.state.last_srv = None;
 
-    // Read service document
-    log::info!("     Fetching CatalogService service document");
-    let srv_doc = match fetch_odata_service_doc(&srv_doc_url).await {
-        Ok(srv_doc) => srv_doc,
+    let client = match select_profile(
+        &app_state,
+        &mut ui.state,
+        query.get("profile").map(String::as_str),
+    ) {
+        Ok(client) => client,
         Err(err) => {
-            *app_state.error_msg.lock().unwrap() = Some(format!("{}", err));
+            ui.state.error_msg = Some(err);
             log::error!("<--- doc_root() ERROR");
             return Ok(build_http_response(
                 app_state,
-                StatusCode::INTERNAL_SERVER_ERROR,
+                ui,
+                StatusCode::NOT_FOUND,
                 tmpl,
             ));
         }
     };
 
-    // From the service document, extract CatalogCollection URL
-    let catalog_collection = match srv_doc
-        .workspace
-        .collections
-        .into_iter()
-        .find(|c| c.href == "CatalogCollection")
-    {
-        Some(cat_coll) => cat_coll,
-        None => {
-            *app_state.error_msg.lock().unwrap() = Some(format!("That's weird, the CatalogService does not have a collection called CatalogCollection"));
+    // Read service document
+    log::info!("     Fetching CatalogService service document");
+    let srv_doc = match client.service_document().await {
+        Ok(srv_doc) => srv_doc,
+        Err(err) => {
+            ui.state.error_msg = Some(err.to_string());
             log::error!("<--- doc_root() ERROR");
             return Ok(build_http_response(
                 app_state,
+                ui,
                 StatusCode::INTERNAL_SERVER_ERROR,
                 tmpl,
             ));
         }
     };
 
+    // Check that the service document has a CatalogCollection
+    if !srv_doc
+        .workspace
+        .collections
+        .iter()
+        .any(|c| c.href == "CatalogCollection")
+    {
+        ui.state.error_msg = Some(
+            "That's weird, the CatalogService does not have a collection called CatalogCollection"
+                .to_string(),
+        );
+        log::error!("<--- doc_root() ERROR");
+        return Ok(build_http_response(
+            app_state,
+            ui,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            tmpl,
+        ));
+    }
+
     // Read the available catalogs
     log::info!("     Fetching CatalogService catalogs");
-    let feed_url = format!("{}{}", srv_doc_url, catalog_collection.href);
-    let catalog_feed = match fetch_feed::<Catalog>(&feed_url).await {
-        Ok(feed) => feed,
+    let catalogs = match client.catalogs().await {
+        Ok(catalogs) => catalogs,
         Err(err) => {
-            *app_state.error_msg.lock().unwrap() = Some(err.to_string());
+            ui.state.error_msg = Some(err.to_string());
             log::error!("<--- doc_root() ERROR");
             return Ok(build_http_response(
                 app_state,
+                ui,
                 StatusCode::INTERNAL_SERVER_ERROR,
                 tmpl,
             ));
         }
     };
 
-    // From the catalog feed, extract the list of available Catalog names
-    if catalog_feed.entries.is_none() {
-        *app_state.error_msg.lock().unwrap() = Some(format!(
+    if catalogs.is_empty() {
+        ui.state.error_msg = Some(format!(
             "No service catalogs have been defined: {}",
-            catalog_feed.id
+            client.service_url()
         ));
         log::error!("<--- doc_root() ERROR");
 
         return Ok(build_http_response(
             app_state,
+            ui,
             StatusCode::INTERNAL_SERVER_ERROR,
             tmpl,
         ));
     }
 
-    let mut catalog_list: Vec<String> = Vec::new();
-    catalog_feed.entries.unwrap().into_iter().for_each(|c| {
-        catalog_list.push(c.content.properties.unwrap().title);
-    });
-
-    *app_state.catalog_list.lock().unwrap() = Some(catalog_list);
+    ui.state.catalog_list = Some(catalogs.into_iter().map(|c| c.id).collect());
 
     log::info!("<--- doc_root()");
-    Ok(build_http_response(app_state, StatusCode::OK, tmpl))
+    Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
 }
 
 // ---------------------------------------------------------------------------------------------------------------------
@@ -225,36 +284,68 @@ async fn doc_root(
 // ---------------------------------------------------------------------------------------------------------------------
 #[derive(Debug, Deserialize)]
 pub struct FetchServicesQS {
+    profile: Option<String>,
     catalog_name: String,
+    /// Set by the checkbox that also reads each service's entity sets, tags and annotation files
+    expand: Option<String>,
 }
 
 #[get("/fetchServices")]
 async fn catalog_services<'template>(
+    req: HttpRequest,
     qs: web::Query<FetchServicesQS>,
     app_state: web::Data<AppState>,
     tmpl: web::Data<TinyTemplate<'template>>,
 ) -> Result<HttpResponse, Error> {
     log::info!("---> catalog_services()");
-    let services_url = format!(
-        "https://{}/{}/{}/CatalogCollection('{}')/Services",
-        app_state.hostname,
-        str::from_utf8(HOST_PATH).unwrap(),
-        str::from_utf8(SERVICE_NAME).unwrap(),
-        qs.catalog_name
-    );
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- catalog_services() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
 
     // Read services in selected catalog
     log::info!("     Fetching services in catalog {}", qs.catalog_name);
-    let services_feed = match fetch_feed::<Service>(&services_url).await {
-        Ok(feed) => feed,
+    // With $expand, the navigation properties of every service arrive in the same response
+    ui.state.service_details = None;
+    let services = if qs.expand.is_some() {
+        client
+            .expanded_services(&qs.catalog_name)
+            .await
+            .map(|expanded| {
+                let mut details: Vec<ServiceDetails> =
+                    expanded.iter().map(ServiceDetails::from).collect();
+                details.sort_by(|a, b| a.id.cmp(&b.id));
+                ui.state.service_details = Some(details);
+
+                expanded.into_iter().map(|srv| srv.service).collect()
+            })
+    } else {
+        client.services(&qs.catalog_name).await
+    };
+
+    let services = match services {
+        Ok(services) => services,
         Err(e) => {
-            *app_state.error_msg.lock().unwrap() = Some(format!(
+            ui.state.error_msg = Some(format!(
                 "{}\nAn error occurred trying to read the Services in catalog {}",
                 e, qs.catalog_name
             ));
             log::error!("<--- catalog_services() ERROR");
             return Ok(build_http_response(
                 app_state,
+                ui,
                 StatusCode::INTERNAL_SERVER_ERROR,
                 tmpl,
             ));
@@ -262,251 +353,2143 @@ async fn catalog_services<'template>(
     };
 
     // Build service list
-    if services_feed.entries.is_none() {
-        *app_state.error_msg.lock().unwrap() =
-            Some(format!("No services found: {}", services_feed.id));
+    if services.is_empty() {
+        ui.state.error_msg = Some(format!("No services found in catalog {}", qs.catalog_name));
         log::error!("<--- catalog_services() ERROR");
         return Ok(build_http_response(
             app_state,
+            ui,
             StatusCode::INTERNAL_SERVER_ERROR,
             tmpl,
         ));
     }
 
-    let mut service_list: Vec<(String, String)> = Vec::new();
-    services_feed.entries.unwrap().into_iter().for_each(|srv| {
-        let props = srv.content.properties.unwrap().clone();
-        service_list.push((props.id, props.metadata_url));
-    });
-
-    service_list.sort_by(|a, b| a.0.cmp(&b.0));
-
-    let first_srv = service_list[0].1.clone();
-
-    *app_state.last_srv.lock().unwrap() = Some(first_srv);
-    *app_state.service_list.lock().unwrap() = Some(service_list);
+    ui.state.service_count = Some(format!(
+        "{} services in catalog {}",
+        services.len(),
+        qs.catalog_name
+    ));
+    set_service_list(&mut ui.state, services, Some(&qs.catalog_name));
     log::info!("<--- catalog_services()");
 
-    return Ok(build_http_response(app_state, StatusCode::OK, tmpl));
+    Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
 }
 
 // ---------------------------------------------------------------------------------------------------------------------
-// Fetch service metadata
+// Find services matching the search criteria
 // ---------------------------------------------------------------------------------------------------------------------
 #[derive(Debug, Deserialize)]
-pub struct FetchMetadataQS {
-    url: String,
+pub struct SearchServicesQS {
+    profile: Option<String>,
+    #[serde(flatten)]
+    criteria: ServiceSearch,
 }
 
-#[get("/fetchMetadata")]
-async fn fetch_metadata<'template>(
-    qs: web::Query<FetchMetadataQS>,
+#[get("/searchServices")]
+async fn search_services<'template>(
+    req: HttpRequest,
+    qs: web::Query<SearchServicesQS>,
     app_state: web::Data<AppState>,
     tmpl: web::Data<TinyTemplate<'template>>,
 ) -> Result<HttpResponse, Error> {
-    log::info!("---> fetch_metadata()");
-    *app_state.last_srv.lock().unwrap() = Some(qs.url.clone());
+    log::info!("---> search_services()");
 
-    let auth_chars = match fetch_auth() {
-        Ok(auth_chars) => auth_chars,
+    let mut ui = app_state.ui_sessions.load(&req);
+    let qs = qs.into_inner();
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
         Err(err) => {
-            *app_state.error_msg.lock().unwrap() = Some(err);
-            log::error!("<--- fetch_metadata() ERROR");
+            ui.state.error_msg = Some(err);
+            log::error!("<--- search_services() ERROR");
             return Ok(build_http_response(
                 app_state,
-                StatusCode::INTERNAL_SERVER_ERROR,
+                ui,
+                StatusCode::NOT_FOUND,
                 tmpl,
             ));
         }
     };
 
-    log::info!("GET: {}", qs.url);
+    ui.state.service_search = qs.criteria.clone();
+    ui.state.service_list = None;
+    ui.state.service_details = None;
+    ui.state.service_count = None;
 
-    let client = reqwest::Client::new();
-    let response = match client
-        .get(qs.url.clone())
-        .header("Authorization", format!("Basic {}", auth_chars))
-        .send()
-        .await
-    {
-        Ok(response) => response,
+    // Reading the whole of a large ServiceCollection is exactly what a search avoids
+    if qs.criteria.is_empty() {
+        ui.state.error_msg = Some("Enter at least one search criterion".to_owned());
+        log::error!("<--- search_services() ERROR");
+        return Ok(build_http_response(
+            app_state,
+            ui,
+            StatusCode::BAD_REQUEST,
+            tmpl,
+        ));
+    }
+
+    let query = match qs.criteria.to_query() {
+        Ok(query) => query.top(MAX_SEARCH_RESULTS).inline_count(),
         Err(err) => {
-            *app_state.error_msg.lock().unwrap() = Some(err.to_string());
-            log::error!("<--- fetch_metadata() ERROR");
+            ui.state.error_msg = Some(err);
+            log::error!("<--- search_services() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::BAD_REQUEST,
+                tmpl,
+            ));
+        }
+    };
+
+    log::info!("     Searching services");
+    let found = match client.read_all::<Service>(&query).await {
+        Ok(found) => found,
+        Err(err) => {
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to search the ServiceCollection",
+                err
+            ));
+            log::error!("<--- search_services() ERROR");
             return Ok(build_http_response(
                 app_state,
-                StatusCode::from_u16(err.status().unwrap().as_u16()).unwrap(),
+                ui,
+                StatusCode::INTERNAL_SERVER_ERROR,
                 tmpl,
             ));
         }
     };
 
-    let http_status_code = StatusCode::from_u16(response.status().as_u16()).unwrap();
-    log::info!("HTTP Status code = {}", http_status_code);
+    if found.entries.is_empty() {
+        ui.state.error_msg = Some("No services match the search criteria".to_owned());
+        log::info!("<--- search_services()");
+        return Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl));
+    }
 
-    let raw_xml = response.text().await.unwrap();
+    let shown = found.entries.len();
+    ui.state.service_count = Some(match found.total_count {
+        Some(total) if total > shown => format!("Showing {} of {} matching services", shown, total),
+        _ => format!("{} matching services", shown),
+    });
+    ui.state.error_msg = None;
+    set_service_list(&mut ui.state, found.entries, None);
+    log::info!("<--- search_services()");
 
-    match http_status_code {
-        StatusCode::OK => {
-            *app_state.error_msg.lock().unwrap() = None;
-            log::info!("<--- fetch_metadata()");
-            // Dump the raw XML on the client
-            Ok(HttpResponse::build(http_status_code)
-                .content_type("text/plain")
-                .body(raw_xml))
-        }
-        StatusCode::UNAUTHORIZED => {
-            *app_state.error_msg.lock().unwrap() = Some("Logon failed".to_owned());
-            log::error!("<--- fetch_metadata() ERROR");
-            Ok(build_http_response(app_state, http_status_code, tmpl))
+    Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Find the best matching version of a service
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct BestMatchingServiceQS {
+    profile: Option<String>,
+    #[serde(flatten)]
+    form: BestMatchForm,
+}
+
+#[get("/bestMatchingService")]
+async fn best_matching_service<'template>(
+    req: HttpRequest,
+    qs: web::Query<BestMatchingServiceQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> best_matching_service()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+    let qs = qs.into_inner();
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- best_matching_service() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
         }
-        StatusCode::NOT_FOUND => {
-            *app_state.error_msg.lock().unwrap() = Some("Service not found.  This may be because the service has been defined, but not activated.".to_owned());
-            log::error!("<--- fetch_metadata() ERROR");
-            Ok(build_http_response(app_state, http_status_code, tmpl))
+    };
+
+    ui.state.best_match_form = qs.form.clone();
+    ui.state.best_match = None;
+
+    let (technical_service_name, version_min, version_max) = match qs.form.parameters() {
+        Ok(parameters) => parameters,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- best_matching_service() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::BAD_REQUEST,
+                tmpl,
+            ));
         }
-        StatusCode::INTERNAL_SERVER_ERROR => {
-            *app_state.error_msg.lock().unwrap() = Some(parse_odata_error(&raw_xml));
-            log::error!("<--- fetch_metadata() ERROR");
-            Ok(build_http_response(app_state, http_status_code, tmpl))
+    };
+
+    log::info!(
+        "     Calling BestMatchingService for {} versions {} to {}",
+        technical_service_name,
+        version_min,
+        version_max
+    );
+    match client
+        .best_matching_service(&technical_service_name, version_min, version_max)
+        .await
+    {
+        Ok(service) => {
+            ui.state.best_match = Some(BestMatch::from(service));
+            ui.state.error_msg = None;
+            log::info!("<--- best_matching_service()");
+            Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
         }
-        _ => {
-            *app_state.error_msg.lock().unwrap() = Some(raw_xml);
-            log::error!("<--- fetch_metadata() ERROR");
-            Ok(build_http_response(app_state, http_status_code, tmpl))
+        Err(err) => {
+            let status = error_status(&err);
+            ui.state.error_msg = Some(format!(
+                "{}\nNo version of {} between {} and {} could be found",
+                err, technical_service_name, version_min, version_max
+            ));
+            log::error!("<--- best_matching_service() ERROR");
+            Ok(build_http_response(app_state, ui, status, tmpl))
         }
     }
 }
 
 // ---------------------------------------------------------------------------------------------------------------------
-// Utility functions
+// Fetch service metadata
 // ---------------------------------------------------------------------------------------------------------------------
-async fn fetch_feed<T>(feed_url: &str) -> Result<Feed<T>, anyhow::Error>
-where
-    T: DeserializeOwned,
-{
-    log::info!("---> fetch_feed<T>()");
-    let client = reqwest::Client::new();
+#[derive(Debug, Deserialize)]
+pub struct FetchMetadataQS {
+    profile: Option<String>,
+    url: String,
+}
 
-    let auth_chars = match fetch_auth() {
-        Ok(auth_chars) => auth_chars,
-        Err(e) => return Err(anyhow!(e)),
-    };
+#[get("/fetchMetadata")]
+async fn fetch_metadata<'template>(
+    req: HttpRequest,
+    qs: web::Query<FetchMetadataQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> fetch_metadata()");
 
-    log::info!("GET: {}", feed_url);
+    let mut ui = app_state.ui_sessions.load(&req);
 
-    let response = match client
-        .get(feed_url)
-        .header("Authorization", format!("Basic {}", auth_chars))
-        .send()
-        .await
-    {
-        Ok(response) => response,
-        Err(e) => {
-            log::error!("<--- fetch_feed<T>() ERROR in HTTP Request");
-            return Err(anyhow!(e));
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- fetch_metadata() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
         }
     };
+    ui.state.last_srv = Some(qs.url.clone());
 
-    let http_status_code = response.status();
-    log::info!("HTTP Status code = {}", http_status_code);
+    let err = match client.metadata_at(&qs.url).await {
+        Ok(raw_xml) => {
+            ui.state.error_msg = None;
+            log::info!("<--- fetch_metadata()");
+            // Dump the raw XML on the client
+            let mut response = HttpResponse::build(StatusCode::OK)
+                .content_type("text/plain")
+                .body(raw_xml);
+            app_state.ui_sessions.save(ui, &mut response);
+            return Ok(response);
+        }
+        Err(err) => err,
+    };
 
-    let raw_xml = response.text().await.unwrap();
+    let http_status_code = error_status(&err);
 
-    match http_status_code {
-        reqwest::StatusCode::OK => match Feed::<T>::from_str(&raw_xml) {
-            Ok(feed) => {
-                log::info!("<--- fetch_feed<T>()");
-                Ok(feed)
-            }
-            Err(e) => {
-                log::error!("<--- fetch_feed<T>() ERROR in XML deserialization");
-                Err(anyhow!(e))
-            }
-        },
-        _ => {
-            log::error!("<--- fetch_feed<T>() ERROR {}", http_status_code);
-            Err(anyhow!(parse_odata_error(&raw_xml)))
-        }
-    }
+    ui.state.error_msg = Some(match (http_status_code, err) {
+        (StatusCode::UNAUTHORIZED, _) => "Logon failed".to_owned(),
+        (StatusCode::NOT_FOUND, _) => "Service not found.  This may be because the service has been defined, but not activated.".to_owned(),
+        (_, CatalogError::Status { message, .. }) => message,
+        (_, err) => err.to_string(),
+    });
+    log::error!("<--- fetch_metadata() ERROR");
+
+    Ok(build_http_response(app_state, ui, http_status_code, tmpl))
 }
 
-// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
-async fn fetch_odata_service_doc(srv_doc_url: &str) -> Result<AtomService, anyhow::Error> {
-    log::info!("---> fetch_odata_service_doc()");
-    let client = reqwest::Client::new();
+// ---------------------------------------------------------------------------------------------------------------------
+// Save a service's metadata in the project directory, where parse-sap-odata's build script expects it
+// ---------------------------------------------------------------------------------------------------------------------
+/// `overwrite` is set by the button shown when the saved file differs from the service's current metadata
+#[derive(Debug, Deserialize)]
+pub struct SaveMetadataForm {
+    profile: Option<String>,
+    service_id: String,
+    overwrite: Option<bool>,
+}
 
-    let auth_chars = match fetch_auth() {
-        Ok(auth_chars) => auth_chars,
-        Err(e) => {
-            log::info!("<--- fetch_odata_service_doc()");
-            return Err(anyhow!(e));
+#[post("/saveMetadata")]
+async fn save_metadata<'template>(
+    req: HttpRequest,
+    form: web::Form<SaveMetadataForm>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> save_metadata()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, form.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- save_metadata() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
         }
     };
 
-    log::info!("GET: {}", srv_doc_url);
+    let Some(project) = app_state.project.clone() else {
+        ui.state.error_msg = Some("No project directory has been configured".to_owned());
+        log::error!("<--- save_metadata() ERROR");
+        return Ok(build_http_response(
+            app_state,
+            ui,
+            StatusCode::CONFLICT,
+            tmpl,
+        ));
+    };
 
-    let response = match client
-        .get(srv_doc_url)
-        .header("Authorization", format!("Basic {}", auth_chars))
-        .send()
-        .await
-    {
-        Ok(response) => response,
-        Err(e) => {
-            log::info!("<--- fetch_odata_service_doc()");
-            return Err(anyhow!(e));
+    let (service, raw_xml) = match service_metadata(&client, &form.service_id).await {
+        Ok(metadata) => metadata,
+        Err((status, err_msg)) => {
+            ui.state.error_msg = Some(err_msg);
+            log::error!("<--- save_metadata() ERROR");
+            return Ok(build_http_response(app_state, ui, status, tmpl));
         }
     };
+    ui.state.last_srv = Some(service.metadata_url.clone());
 
-    let http_status_code = response.status();
-    log::info!("HTTP Status code = {}", http_status_code);
-
-    let raw_xml = response.text().await.unwrap();
+    let file_name = metadata_file_name(
+        &service.technical_service_name,
+        service.technical_service_version,
+    );
 
-    log::info!("<--- fetch_odata_service_doc()");
-    match http_status_code {
-        reqwest::StatusCode::OK => match AtomService::from_str(&raw_xml) {
-            Ok(srv_doc) => Ok(srv_doc),
-            Err(err) => Err(anyhow!(err)),
-        },
-        _ => Err(anyhow!(parse_odata_error(&raw_xml))),
+    match project.save_metadata(&file_name, &raw_xml, form.overwrite.unwrap_or(false)) {
+        Ok(summary) => {
+            ui.state.info_msg = Some(match summary.outcome {
+                SaveOutcome::Created => format!("Metadata saved as {}", summary.path.display()),
+                SaveOutcome::Replaced => format!("{} replaced", summary.path.display()),
+                SaveOutcome::Unchanged => format!("{} is up to date", summary.path.display()),
+                SaveOutcome::NotOverwritten => format!(
+                    "{} differs from the current metadata of service {}",
+                    summary.path.display(),
+                    service.id
+                ),
+            });
+            ui.state.metadata_save = Some((service.id, summary));
+            ui.state.error_msg = None;
+            log::info!("<--- save_metadata()");
+            Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+        }
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- save_metadata() ERROR");
+            Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                tmpl,
+            ))
+        }
     }
 }
 
-// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
-fn parse_odata_error(raw_xml: &str) -> String {
-    match ODataError::from_str(&raw_xml) {
-        Ok(odata_error) => format!("{:#?}", odata_error.message),
-        Err(err) => format!("{err:#?}"),
-    }
+// ---------------------------------------------------------------------------------------------------------------------
+// Clear a service's metadata cache in the Gateway hub
+//
+// Since this affects every user of the SAP system, the profile must opt in, and the user must confirm the request
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct ClearMetadataCacheQS {
+    profile: Option<String>,
+    service_id: String,
 }
 
-// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
-fn build_http_response<'template>(
+#[get("/clearMetadataCache")]
+async fn confirm_cache_clear<'template>(
+    req: HttpRequest,
+    qs: web::Query<ClearMetadataCacheQS>,
     app_state: web::Data<AppState>,
-    status_code: StatusCode,
     tmpl: web::Data<TinyTemplate<'template>>,
-) -> HttpResponse {
-    let response_body = tmpl
-        .render(
-            "index.html",
-            &json!({
-              "hostName": app_state.hostname,
-              "catalogList": *app_state.catalog_list.lock().unwrap(),
-              "serviceList": *app_state.service_list.lock().unwrap(),
-              "errMsg": *app_state.error_msg.lock().unwrap(),
-              "lastSrv": *app_state.last_srv.lock().unwrap()
-            }),
-        )
-        .map_err(|err| error::ErrorInternalServerError(format!("Template error\n{}", err)))
-        .unwrap();
+) -> Result<HttpResponse, Error> {
+    log::info!("---> confirm_cache_clear()");
 
-    HttpResponse::build(status_code)
-        .content_type("text/html; charset=utf-8")
-        .body(response_body)
-}
+    let mut ui = app_state.ui_sessions.load(&req);
 
-// ---------------------------------------------------------------------------------------------------------------------
-#[cfg(test)]
-pub mod unit_tests;
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- confirm_cache_clear() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    if !client.profile().allow_cache_clear {
+        ui.state.pending_cache_clear = None;
+        ui.state.error_msg = Some(format!(
+            "Clearing the metadata cache is not enabled for SAP system profile {}",
+            client.profile().name
+        ));
+        log::error!("<--- confirm_cache_clear() ERROR");
+        return Ok(build_http_response(
+            app_state,
+            ui,
+            StatusCode::FORBIDDEN,
+            tmpl,
+        ));
+    }
+
+    ui.state.pending_cache_clear = Some(qs.service_id.clone());
+    ui.state.error_msg = None;
+    log::info!("<--- confirm_cache_clear()");
+
+    Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+}
+
+/// Submitted by the confirmation form.  `cancel` is set by the Cancel button.
+#[derive(Debug, Deserialize)]
+pub struct ClearMetadataCacheForm {
+    profile: Option<String>,
+    service_id: String,
+    cancel: Option<String>,
+}
+
+#[post("/clearMetadataCache")]
+async fn clear_metadata_cache<'template>(
+    req: HttpRequest,
+    form: web::Form<ClearMetadataCacheForm>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> clear_metadata_cache()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, form.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- clear_metadata_cache() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    // Only the service this browser was asked to confirm may be cleared, and only once
+    if ui.state.pending_cache_clear.take().as_deref() != Some(&form.service_id) {
+        ui.state.error_msg = Some(format!(
+            "Clearing the metadata cache of service {} has not been confirmed",
+            form.service_id
+        ));
+        log::error!("<--- clear_metadata_cache() ERROR");
+        return Ok(build_http_response(
+            app_state,
+            ui,
+            StatusCode::CONFLICT,
+            tmpl,
+        ));
+    }
+
+    if form.cancel.is_some() {
+        ui.state.error_msg = None;
+        log::info!("<--- clear_metadata_cache() cancelled");
+        return Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl));
+    }
+
+    log::info!(
+        "     Calling ClearMetadataCacheForService for {}",
+        form.service_id
+    );
+    let service = match client.clear_metadata_cache(&form.service_id).await {
+        Ok(service) => service,
+        Err(err) => {
+            let status = error_status(&err);
+            ui.state.error_msg = Some(format!(
+                "{}\nThe metadata cache of service {} could not be cleared",
+                err, form.service_id
+            ));
+            log::error!("<--- clear_metadata_cache() ERROR");
+            return Ok(build_http_response(app_state, ui, status, tmpl));
+        }
+    };
+
+    // Reading the metadata again makes the Gateway rebuild its cache from the backend
+    log::info!("     Fetching refreshed metadata of {}", service.id);
+    ui.state.last_srv = Some(service.metadata_url.clone());
+    match client.metadata(&service).await {
+        Ok(raw_xml) => {
+            ui.state.info_msg = Some(format!(
+                "The metadata cache of service {} has been cleared, and its metadata read again",
+                service.id
+            ));
+            ui.state.refreshed_metadata = Some(raw_xml);
+            ui.state.error_msg = None;
+            log::info!("<--- clear_metadata_cache()");
+            Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+        }
+        Err(err) => {
+            ui.state.info_msg = Some(format!(
+                "The metadata cache of service {} has been cleared",
+                service.id
+            ));
+            ui.state.error_msg = Some(format!("{}\nThe refreshed metadata could not be read", err));
+            log::error!("<--- clear_metadata_cache() ERROR");
+            Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::BAD_GATEWAY,
+                tmpl,
+            ))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// List the annotation files known to the Gateway
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct AnnotationsQS {
+    profile: Option<String>,
+}
+
+#[get("/annotations")]
+async fn annotations<'template>(
+    req: HttpRequest,
+    qs: web::Query<AnnotationsQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> annotations()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- annotations() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    ui.state.annotation_usage = None;
+
+    log::info!("     Fetching annotation files");
+    match client.annotations().await {
+        Ok(mut annotations) => {
+            annotations.sort_by(|a, b| {
+                (&a.technical_name, &a.version).cmp(&(&b.technical_name, &b.version))
+            });
+            ui.state.annotation_list = Some(annotations);
+            ui.state.error_msg = None;
+            log::info!("<--- annotations()");
+            Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+        }
+        Err(err) => {
+            ui.state.annotation_list = None;
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to read the Annotations",
+                err
+            ));
+            log::error!("<--- annotations() ERROR");
+            Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                tmpl,
+            ))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Show the services that use an annotation file
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct AnnotationQS {
+    profile: Option<String>,
+    technical_name: String,
+    version: String,
+}
+
+#[get("/annotationServices")]
+async fn annotation_services<'template>(
+    req: HttpRequest,
+    qs: web::Query<AnnotationQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> annotation_services()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- annotation_services() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    ui.state.annotation_usage = None;
+
+    log::info!(
+        "     Fetching services that use annotation file {} version {}",
+        qs.technical_name,
+        qs.version
+    );
+    match client
+        .annotation_services(&qs.technical_name, &qs.version)
+        .await
+    {
+        Ok(services) => {
+            let mut services: Vec<(String, String)> = services
+                .into_iter()
+                .map(|srv| (srv.id, srv.metadata_url))
+                .collect();
+            services.sort_by(|a, b| a.0.cmp(&b.0));
+
+            ui.state.annotation_usage = Some(AnnotationUsage {
+                technical_name: qs.technical_name.clone(),
+                version: qs.version.clone(),
+                services,
+            });
+            ui.state.error_msg = None;
+            log::info!("<--- annotation_services()");
+            Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+        }
+        Err(err) => {
+            let status = error_status(&err);
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to read the services that use annotation file {} version {}",
+                err, qs.technical_name, qs.version
+            ));
+            log::error!("<--- annotation_services() ERROR");
+            Ok(build_http_response(app_state, ui, status, tmpl))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Download an annotation file
+// ---------------------------------------------------------------------------------------------------------------------
+#[get("/annotationFile")]
+async fn annotation_file<'template>(
+    req: HttpRequest,
+    qs: web::Query<AnnotationQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> annotation_file()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- annotation_file() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    match client
+        .annotation_file(&qs.technical_name, &qs.version)
+        .await
+    {
+        Ok(raw_xml) => {
+            log::info!("<--- annotation_file()");
+            let mut response = HttpResponse::build(StatusCode::OK)
+                .content_type("application/xml")
+                .insert_header((
+                    "Content-Disposition",
+                    format!(
+                        "attachment; filename=\"{}\"",
+                        download_file_name(&qs.technical_name, &qs.version)
+                    ),
+                ))
+                .body(raw_xml);
+            app_state.ui_sessions.save(ui, &mut response);
+            Ok(response)
+        }
+        Err(err) => {
+            let status = error_status(&err);
+            ui.state.error_msg = Some(format!(
+                "{}\nAnnotation file {} version {} could not be read",
+                err, qs.technical_name, qs.version
+            ));
+            log::error!("<--- annotation_file() ERROR");
+            Ok(build_http_response(app_state, ui, status, tmpl))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// List the vocabularies known to the Gateway
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct VocabulariesQS {
+    profile: Option<String>,
+}
+
+#[get("/vocabularies")]
+async fn show_vocabularies<'template>(
+    req: HttpRequest,
+    qs: web::Query<VocabulariesQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> show_vocabularies()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- show_vocabularies() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    log::info!("     Fetching vocabularies");
+    match client.vocabularies().await {
+        Ok(vocabularies) => {
+            set_vocabulary_list(&mut ui.state, &app_state.vocabularies, &vocabularies);
+            ui.state.error_msg = None;
+            log::info!("<--- show_vocabularies()");
+            Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+        }
+        Err(err) => {
+            ui.state.vocabulary_list = None;
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to read the Vocabularies",
+                err
+            ));
+            log::error!("<--- show_vocabularies() ERROR");
+            Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                tmpl,
+            ))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Download a vocabulary file
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct VocabularyQS {
+    profile: Option<String>,
+    technical_name: String,
+    version: String,
+    sap_origin: String,
+}
+
+#[get("/vocabularyFile")]
+async fn vocabulary_file<'template>(
+    req: HttpRequest,
+    qs: web::Query<VocabularyQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> vocabulary_file()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+    let qs = qs.into_inner();
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- vocabulary_file() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    let vocabulary = Vocabulary {
+        technical_name: qs.technical_name,
+        version: qs.version,
+        sap_origin: qs.sap_origin,
+        ..Default::default()
+    };
+
+    match client.vocabulary_file(&vocabulary).await {
+        Ok(raw_xml) => {
+            log::info!("<--- vocabulary_file()");
+            let mut response = HttpResponse::build(StatusCode::OK)
+                .content_type("application/xml")
+                .insert_header((
+                    "Content-Disposition",
+                    format!(
+                        "attachment; filename=\"{}\"",
+                        download_file_name(&vocabulary.technical_name, &vocabulary.version)
+                    ),
+                ))
+                .body(raw_xml);
+            app_state.ui_sessions.save(ui, &mut response);
+            Ok(response)
+        }
+        Err(err) => {
+            let status = error_status(&err);
+            ui.state.error_msg = Some(format!(
+                "{}\nVocabulary {} version {} could not be read",
+                err, vocabulary.technical_name, vocabulary.version
+            ));
+            log::error!("<--- vocabulary_file() ERROR");
+            Ok(build_http_response(app_state, ui, status, tmpl))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Store every vocabulary in the local vocabulary directory
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct StoreVocabulariesForm {
+    profile: Option<String>,
+}
+
+#[post("/storeVocabularies")]
+async fn store_vocabularies<'template>(
+    req: HttpRequest,
+    form: web::Form<StoreVocabulariesForm>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> store_vocabularies()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, form.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- store_vocabularies() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    let vocabularies = match client.vocabularies().await {
+        Ok(vocabularies) => vocabularies,
+        Err(err) => {
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to read the Vocabularies",
+                err
+            ));
+            log::error!("<--- store_vocabularies() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                tmpl,
+            ));
+        }
+    };
+
+    // One vocabulary that cannot be read does not stop the others from being stored
+    let mut stored = 0;
+    let mut failures: Vec<String> = Vec::new();
+
+    for vocabulary in vocabularies.iter() {
+        log::info!(
+            "     Storing vocabulary {} version {}",
+            vocabulary.technical_name,
+            vocabulary.version
+        );
+        let result = match client.vocabulary_file(vocabulary).await {
+            Ok(raw_xml) => app_state.vocabularies.save(vocabulary, &raw_xml),
+            Err(err) => Err(err.to_string()),
+        };
+
+        match result {
+            Ok(_) => stored += 1,
+            Err(err) => failures.push(format!("{}: {}", vocabulary.technical_name, err)),
+        }
+    }
+
+    set_vocabulary_list(&mut ui.state, &app_state.vocabularies, &vocabularies);
+    ui.state.info_msg = Some(format!(
+        "{} of {} vocabularies stored in {}",
+        stored,
+        vocabularies.len(),
+        app_state.vocabularies.dir().display()
+    ));
+
+    if failures.is_empty() {
+        ui.state.error_msg = None;
+        log::info!("<--- store_vocabularies()");
+        Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+    } else {
+        ui.state.error_msg = Some(failures.join("\n"));
+        log::error!("<--- store_vocabularies() ERROR");
+        Ok(build_http_response(
+            app_state,
+            ui,
+            StatusCode::BAD_GATEWAY,
+            tmpl,
+        ))
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// List tags, most frequently used first
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct TagsQS {
+    profile: Option<String>,
+}
+
+#[get("/tags")]
+async fn show_tags<'template>(
+    req: HttpRequest,
+    qs: web::Query<TagsQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> show_tags()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- show_tags() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    log::info!("     Fetching tags");
+    match client.tags().await {
+        Ok(tags) => {
+            ui.state.tag_list = Some(TagRow::ranked(tags));
+            ui.state.error_msg = None;
+            log::info!("<--- show_tags()");
+            Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+        }
+        Err(err) => {
+            ui.state.tag_list = None;
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to read the TagCollection",
+                err
+            ));
+            log::error!("<--- show_tags() ERROR");
+            Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                tmpl,
+            ))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Display the services that carry the selected tag
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct TagServicesQS {
+    profile: Option<String>,
+    tag_id: String,
+}
+
+#[get("/tagServices")]
+async fn tag_services<'template>(
+    req: HttpRequest,
+    qs: web::Query<TagServicesQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> tag_services()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- tag_services() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    ui.state.service_list = None;
+    ui.state.service_details = None;
+    ui.state.service_count = None;
+
+    log::info!("     Fetching services tagged {}", qs.tag_id);
+    let services = match client.tag_services(&qs.tag_id).await {
+        Ok(services) => services,
+        Err(err) => {
+            let status = error_status(&err);
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to read the services tagged {}",
+                err, qs.tag_id
+            ));
+            log::error!("<--- tag_services() ERROR");
+            return Ok(build_http_response(app_state, ui, status, tmpl));
+        }
+    };
+
+    if services.is_empty() {
+        ui.state.error_msg = Some(format!("No services are tagged {}", qs.tag_id));
+        log::info!("<--- tag_services()");
+        return Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl));
+    }
+
+    ui.state.service_count = Some(format!("{} services tagged {}", services.len(), qs.tag_id));
+    ui.state.error_msg = None;
+    set_service_list(&mut ui.state, services, None);
+    log::info!("<--- tag_services()");
+
+    Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Show everything the catalog knows about one service, on a page that can be bookmarked
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct ServicePageQS {
+    profile: Option<String>,
+    /// The catalog whose details are shown.  Without it, every catalog is shown.
+    catalog: Option<String>,
+}
+
+#[get("/services/{id}")]
+async fn service_page<'template>(
+    req: HttpRequest,
+    path: web::Path<String>,
+    qs: web::Query<ServicePageQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> service_page()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+    let service_id = path.into_inner();
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- service_page() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    log::info!("     Fetching service {}", service_id);
+    let details = match futures_util::try_join!(
+        client.service(&service_id),
+        client.entity_sets(&service_id),
+        client.catalogs()
+    ) {
+        Ok(details) => details,
+        Err(err) => {
+            let status = error_status(&err);
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to read service {}",
+                err, service_id
+            ));
+            log::error!("<--- service_page() ERROR");
+            return Ok(build_http_response(app_state, ui, status, tmpl));
+        }
+    };
+
+    let (service, mut entity_sets, mut catalogs) = details;
+    entity_sets.sort_by(|a, b| a.id.cmp(&b.id));
+
+    if let Some(catalog_id) = &qs.catalog {
+        catalogs.retain(|cat| &cat.id == catalog_id);
+    }
+
+    let profile_name = ui.state.profile.clone().unwrap_or_default();
+    let response_body = tmpl
+        .render(
+            "service.html",
+            &json!({
+              "hostName": client.profile().hostname,
+              "profile": profile_name,
+              "service": service,
+              "entitySets": entity_sets,
+              "catalogs": catalogs
+            }),
+        )
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error\n{}", err)))?;
+
+    let mut response = HttpResponse::build(StatusCode::OK)
+        .content_type("text/html; charset=utf-8")
+        .body(response_body);
+    app_state.ui_sessions.save(ui, &mut response);
+    log::info!("<--- service_page()");
+
+    Ok(response)
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Check which services in a catalog can actually be called
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct ProbeCatalogQS {
+    profile: Option<String>,
+    catalog_name: String,
+}
+
+#[get("/probeServices")]
+async fn probe_catalog<'template>(
+    req: HttpRequest,
+    qs: web::Query<ProbeCatalogQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> probe_catalog()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- probe_catalog() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    ui.state.probe_results = None;
+
+    log::info!("     Fetching services in catalog {}", qs.catalog_name);
+    let services = match client.services(&qs.catalog_name).await {
+        Ok(services) => services,
+        Err(err) => {
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to read the Services in catalog {}",
+                err, qs.catalog_name
+            ));
+            log::error!("<--- probe_catalog() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                tmpl,
+            ));
+        }
+    };
+
+    log::info!(
+        "     Probing {} services, {} at a time",
+        services.len(),
+        DEFAULT_CONCURRENCY
+    );
+    let mut results = probe_services(&client, &services, DEFAULT_CONCURRENCY).await;
+    results.sort_by(|a, b| a.service_id.cmp(&b.service_id));
+
+    let available = results
+        .iter()
+        .filter(|result| result.status == ProbeStatus::Ok)
+        .count();
+    ui.state.service_count = Some(format!(
+        "{} of {} services in catalog {} responded",
+        available,
+        results.len(),
+        qs.catalog_name
+    ));
+    ui.state.service_details = None;
+    ui.state.probe_results = Some(results);
+    ui.state.error_msg = None;
+    set_service_list(&mut ui.state, services, Some(&qs.catalog_name));
+    log::info!("<--- probe_catalog()");
+
+    Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Export the metadata of every service in a catalog, or of selected services, as a zip archive or into the project
+//
+// The zip archive is built in memory and sent once it is complete
+// ---------------------------------------------------------------------------------------------------------------------
+/// `service_ids` is a comma separated list.  With a catalog, it narrows the export down to those services of the
+/// catalog; without one, it names the services to export.  `to_project` writes the export into the project directory
+/// instead of downloading it.
+#[derive(Debug, Deserialize)]
+pub struct ExportMetadataForm {
+    profile: Option<String>,
+    catalog_name: Option<String>,
+    service_ids: Option<String>,
+    to_project: Option<bool>,
+}
+
+#[post("/exportMetadata")]
+async fn export_catalog_metadata<'template>(
+    req: HttpRequest,
+    form: web::Form<ExportMetadataForm>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> export_catalog_metadata()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, form.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- export_catalog_metadata() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    let project = match (form.to_project.unwrap_or(false), app_state.project.clone()) {
+        (false, _) => None,
+        (true, Some(project)) => Some(project),
+        (true, None) => {
+            ui.state.error_msg = Some("No project directory has been configured".to_owned());
+            log::error!("<--- export_catalog_metadata() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::CONFLICT,
+                tmpl,
+            ));
+        }
+    };
+
+    let catalog_name = form.catalog_name.as_deref().filter(|name| !name.is_empty());
+    let service_ids: Option<Vec<&str>> = form.service_ids.as_deref().map(service_id_list);
+
+    let services = match (catalog_name, &service_ids) {
+        (Some(catalog_name), _) => {
+            log::info!("     Fetching services in catalog {}", catalog_name);
+            client.services(catalog_name).await.map(|services| {
+                services
+                    .into_iter()
+                    .filter(|srv| match &service_ids {
+                        Some(ids) => ids.contains(&srv.id.as_str()),
+                        None => true,
+                    })
+                    .collect::<Vec<Service>>()
+            })
+        }
+        (None, Some(ids)) => services_by_id(&client, ids).await,
+        (None, None) => Ok(Vec::new()),
+    };
+
+    let services = match services {
+        Ok(services) if services.is_empty() => {
+            ui.state.error_msg = Some("There are no services to export".to_owned());
+            log::error!("<--- export_catalog_metadata() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::BAD_REQUEST,
+                tmpl,
+            ));
+        }
+        Ok(services) => services,
+        Err(err) => {
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to read the Services to export",
+                err
+            ));
+            log::error!("<--- export_catalog_metadata() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                tmpl,
+            ));
+        }
+    };
+
+    if let Some(project) = project {
+        log::info!(
+            "     Exporting the metadata of {} services into {}",
+            services.len(),
+            project.root().display()
+        );
+        let exported = export_metadata(
+            &client,
+            &services,
+            catalog_name,
+            &ExportOptions::default(),
+            &mut DirSink::new(project.root()),
+        )
+        .await;
+
+        return match exported {
+            Ok(manifest) => {
+                ui.state.info_msg = Some(format!(
+                    "Metadata of {} services exported into {}, {} failed (see {})",
+                    manifest.exported.len(),
+                    project.root().display(),
+                    manifest.failed.len(),
+                    MANIFEST_FILE
+                ));
+                ui.state.error_msg = None;
+                log::info!(
+                    "<--- export_catalog_metadata() {} exported, {} failed",
+                    manifest.exported.len(),
+                    manifest.failed.len()
+                );
+                Ok(build_http_response(app_state, ui, StatusCode::OK, tmpl))
+            }
+            Err(err) => {
+                ui.state.error_msg = Some(format!("{}\nThe metadata could not be exported", err));
+                log::error!("<--- export_catalog_metadata() ERROR");
+                Ok(build_http_response(
+                    app_state,
+                    ui,
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    tmpl,
+                ))
+            }
+        };
+    }
+
+    log::info!("     Exporting the metadata of {} services", services.len());
+    let mut sink = ZipSink::new(Cursor::new(Vec::new()));
+    let exported = export_metadata(
+        &client,
+        &services,
+        catalog_name,
+        &ExportOptions::default(),
+        &mut sink,
+    )
+    .await
+    .and_then(|manifest| sink.finish().map(|zip| (manifest, zip.into_inner())));
+
+    match exported {
+        Ok((manifest, zip)) => {
+            log::info!(
+                "<--- export_catalog_metadata() {} exported, {} failed",
+                manifest.exported.len(),
+                manifest.failed.len()
+            );
+            let mut response = HttpResponse::build(StatusCode::OK)
+                .content_type("application/zip")
+                .insert_header((
+                    "Content-Disposition",
+                    format!(
+                        "attachment; filename=\"{}_metadata.zip\"",
+                        safe_file_name(catalog_name.unwrap_or("services"))
+                    ),
+                ))
+                .body(zip);
+            app_state.ui_sessions.save(ui, &mut response);
+            Ok(response)
+        }
+        Err(err) => {
+            ui.state.error_msg = Some(format!("{}\nThe metadata could not be exported", err));
+            log::error!("<--- export_catalog_metadata() ERROR");
+            Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                tmpl,
+            ))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Download a Cargo project that generates and uses the Rust bindings of the chosen services
+// ---------------------------------------------------------------------------------------------------------------------
+/// `service_ids` is a comma separated list
+#[derive(Debug, Deserialize)]
+pub struct ScaffoldProjectForm {
+    profile: Option<String>,
+    project_name: String,
+    service_ids: String,
+}
+
+#[post("/scaffoldProject")]
+async fn scaffold_project<'template>(
+    req: HttpRequest,
+    form: web::Form<ScaffoldProjectForm>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> scaffold_project()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, form.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- scaffold_project() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    let service_ids = service_id_list(&form.service_ids);
+    let project_name = form.project_name.trim();
+
+    // Each service's metadata and sample entries are read a few services at a time
+    let services = match services_by_id(&client, &service_ids).await {
+        Ok(services) => {
+            stream::iter(services.iter())
+                .map(|srv| ScaffoldService::read(&client, srv))
+                .buffered(DEFAULT_CONCURRENCY)
+                .try_collect::<Vec<ScaffoldService>>()
+                .await
+        }
+        Err(err) => Err(err),
+    };
+
+    let services = match services {
+        Ok(services) => services,
+        Err(err) => {
+            let status = error_status(&err);
+            ui.state.error_msg = Some(format!(
+                "{}\nAn error occurred trying to read the services of project {}",
+                err, project_name
+            ));
+            log::error!("<--- scaffold_project() ERROR");
+            return Ok(build_http_response(app_state, ui, status, tmpl));
+        }
+    };
+
+    let zip = ProjectScaffold::new(project_name, services)
+        .and_then(|scaffold| scaffold.write_zip(Cursor::new(Vec::new())));
+
+    match zip {
+        Ok(zip) => {
+            log::info!("<--- scaffold_project()");
+            let mut response = HttpResponse::build(StatusCode::OK)
+                .content_type("application/zip")
+                .insert_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}.zip\"", project_name),
+                ))
+                .body(zip.into_inner());
+            app_state.ui_sessions.save(ui, &mut response);
+            Ok(response)
+        }
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- scaffold_project() ERROR");
+            Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::BAD_REQUEST,
+                tmpl,
+            ))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Preview or download the Rust modules parse-sap-odata generates from a service's metadata
+// ---------------------------------------------------------------------------------------------------------------------
+/// With `file`, that generated file is downloaded instead of the preview being shown
+#[derive(Debug, Deserialize)]
+pub struct ServiceBindingsQS {
+    profile: Option<String>,
+    service_id: String,
+    file: Option<String>,
+}
+
+#[get("/bindings")]
+async fn service_bindings<'template>(
+    req: HttpRequest,
+    qs: web::Query<ServiceBindingsQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> service_bindings()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- service_bindings() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    log::info!("     Fetching metadata of service {}", qs.service_id);
+    let (service, raw_xml) = match service_metadata(&client, &qs.service_id).await {
+        Ok(metadata) => metadata,
+        Err((status, err_msg)) => {
+            ui.state.error_msg = Some(err_msg);
+            log::error!("<--- service_bindings() ERROR");
+            return Ok(build_http_response(app_state, ui, status, tmpl));
+        }
+    };
+    ui.state.last_srv = Some(service.metadata_url.clone());
+
+    let Some(namespace) = schema_namespace(&raw_xml) else {
+        ui.state.error_msg = Some(format!(
+            "The metadata of service {} does not contain a schema",
+            service.id
+        ));
+        log::error!("<--- service_bindings() ERROR");
+        return Ok(build_http_response(
+            app_state,
+            ui,
+            StatusCode::UNPROCESSABLE_ENTITY,
+            tmpl,
+        ));
+    };
+
+    let module_name = metadata_file_name(
+        &service.technical_service_name,
+        service.technical_service_version,
+    );
+
+    // The generator runs as a child process, so wait for it on a thread that may block
+    let generator = app_state.generator.clone();
+    let bindings = web::block(move || generator.generate(&module_name, &namespace, &raw_xml))
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|bindings| bindings);
+
+    let bindings = match bindings {
+        Ok(bindings) => bindings,
+        Err(err) => {
+            ui.state.error_msg = Some(format!(
+                "{}\nRust bindings could not be generated for service {}",
+                err, service.id
+            ));
+            log::error!("<--- service_bindings() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::INTERNAL_SERVER_ERROR,
+                tmpl,
+            ));
+        }
+    };
+
+    let mut response = match qs.file.as_deref() {
+        Some(file_name) => match bindings.file(file_name) {
+            Some(file) => HttpResponse::build(StatusCode::OK)
+                .content_type("text/plain; charset=utf-8")
+                .insert_header((
+                    "Content-Disposition",
+                    format!("attachment; filename=\"{}\"", file.file_name),
+                ))
+                .body(file.source.clone()),
+            None => {
+                ui.state.error_msg = Some(format!(
+                    "{} was not generated for service {}",
+                    file_name, service.id
+                ));
+                log::error!("<--- service_bindings() ERROR");
+                return Ok(build_http_response(
+                    app_state,
+                    ui,
+                    StatusCode::NOT_FOUND,
+                    tmpl,
+                ));
+            }
+        },
+        None => {
+            let profile_name = ui.state.profile.clone().unwrap_or_default();
+            let response_body = tmpl
+                .render(
+                    "bindings.html",
+                    &json!({
+                      "hostName": client.profile().hostname,
+                      "profile": profile_name,
+                      "serviceId": service.id,
+                      "bindings": bindings
+                    }),
+                )
+                .map_err(|err| {
+                    error::ErrorInternalServerError(format!("Template error\n{}", err))
+                })?;
+
+            HttpResponse::build(StatusCode::OK)
+                .content_type("text/html; charset=utf-8")
+                .body(response_body)
+        }
+    };
+
+    app_state.ui_sessions.save(ui, &mut response);
+    log::info!("<--- service_bindings()");
+
+    Ok(response)
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Show a service's metadata as documentation, with an anchor for every entity type and property
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Deserialize)]
+pub struct ServiceDocsQS {
+    profile: Option<String>,
+    service_id: String,
+}
+
+#[get("/docs")]
+async fn service_docs<'template>(
+    req: HttpRequest,
+    qs: web::Query<ServiceDocsQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> service_docs()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- service_docs() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+
+    log::info!("     Fetching metadata of service {}", qs.service_id);
+    let (service, raw_xml) = match service_metadata(&client, &qs.service_id).await {
+        Ok(metadata) => metadata,
+        Err((status, err_msg)) => {
+            ui.state.error_msg = Some(err_msg);
+            log::error!("<--- service_docs() ERROR");
+            return Ok(build_http_response(app_state, ui, status, tmpl));
+        }
+    };
+    ui.state.last_srv = Some(service.metadata_url.clone());
+
+    let model = match EdmxModel::parse(&raw_xml) {
+        Ok(model) => model,
+        Err(err) => {
+            ui.state.error_msg = Some(format!(
+                "{}\nThe metadata of service {} could not be parsed",
+                err, service.id
+            ));
+            log::error!("<--- service_docs() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                tmpl,
+            ));
+        }
+    };
+
+    let profile_name = ui.state.profile.clone().unwrap_or_default();
+    let response_body = tmpl
+        .render(
+            "docs.html",
+            &json!({
+              "hostName": client.profile().hostname,
+              "profile": profile_name,
+              "service": service,
+              "model": model
+            }),
+        )
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error\n{}", err)))?;
+
+    let mut response = HttpResponse::build(StatusCode::OK)
+        .content_type("text/html; charset=utf-8")
+        .body(response_body);
+
+    app_state.ui_sessions.save(ui, &mut response);
+    log::info!("<--- service_docs()");
+
+    Ok(response)
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Compare a service's metadata with the same (or another) service in another SAP system, or with saved metadata
+// ---------------------------------------------------------------------------------------------------------------------
+/// The base is `base_file` in the project's odata directory, or else the service in `base_profile`.  Without either,
+/// it is the service's own metadata file in the project.  `format=json` returns the differences as JSON.
+#[derive(Debug, Deserialize)]
+pub struct CompareMetadataQS {
+    profile: Option<String>,
+    service_id: String,
+    base_profile: Option<String>,
+    base_service_id: Option<String>,
+    base_file: Option<String>,
+    format: Option<String>,
+}
+
+#[get("/diff")]
+async fn compare_metadata<'template>(
+    req: HttpRequest,
+    qs: web::Query<CompareMetadataQS>,
+    app_state: web::Data<AppState>,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> Result<HttpResponse, Error> {
+    log::info!("---> compare_metadata()");
+
+    let mut ui = app_state.ui_sessions.load(&req);
+
+    let client = match select_profile(&app_state, &mut ui.state, qs.profile.as_deref()) {
+        Ok(client) => client,
+        Err(err) => {
+            ui.state.error_msg = Some(err);
+            log::error!("<--- compare_metadata() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::NOT_FOUND,
+                tmpl,
+            ));
+        }
+    };
+    let profile_name = ui.state.profile.clone().unwrap_or_default();
+
+    log::info!("     Fetching metadata of service {}", qs.service_id);
+    let (service, raw_xml) = match service_metadata(&client, &qs.service_id).await {
+        Ok(metadata) => metadata,
+        Err((status, err_msg)) => {
+            ui.state.error_msg = Some(err_msg);
+            log::error!("<--- compare_metadata() ERROR");
+            return Ok(build_http_response(app_state, ui, status, tmpl));
+        }
+    };
+    ui.state.last_srv = Some(service.metadata_url.clone());
+
+    let base_file = qs.base_file.as_deref().filter(|name| !name.is_empty());
+    let base_profile = qs.base_profile.as_deref().filter(|name| !name.is_empty());
+
+    let base = match (base_file, base_profile) {
+        (None, Some(base_profile)) => {
+            let base_service_id = qs
+                .base_service_id
+                .as_deref()
+                .filter(|id| !id.is_empty())
+                .unwrap_or(&service.id);
+
+            match app_state.config.profile(Some(base_profile)) {
+                Ok(profile) => {
+                    log::info!(
+                        "     Fetching metadata of service {} in {}",
+                        base_service_id,
+                        profile.name
+                    );
+                    service_metadata(&app_state.clients[&profile.name], base_service_id)
+                        .await
+                        .map(|(_, base_xml)| {
+                            (format!("{} in {}", base_service_id, profile.name), base_xml)
+                        })
+                }
+                Err(err) => Err((StatusCode::NOT_FOUND, err)),
+            }
+        }
+        (base_file, _) => match &app_state.project {
+            Some(project) => {
+                let file_name = base_file.map(String::from).unwrap_or_else(|| {
+                    metadata_file_name(
+                        &service.technical_service_name,
+                        service.technical_service_version,
+                    )
+                });
+
+                project
+                    .read_metadata(&file_name)
+                    .map(|base_xml| {
+                        (
+                            project.metadata_path(&file_name).display().to_string(),
+                            base_xml,
+                        )
+                    })
+                    .map_err(|err| (StatusCode::NOT_FOUND, err))
+            }
+            None => Err((
+                StatusCode::BAD_REQUEST,
+                "Choose an SAP system to compare with, or configure server.project_dir to compare with saved metadata"
+                    .to_owned(),
+            )),
+        },
+    };
+
+    let (base_name, base_xml) = match base {
+        Ok(base) => base,
+        Err((status, err_msg)) => {
+            ui.state.error_msg = Some(err_msg);
+            log::error!("<--- compare_metadata() ERROR");
+            return Ok(build_http_response(app_state, ui, status, tmpl));
+        }
+    };
+
+    let models = EdmxModel::parse(&base_xml)
+        .map_err(|err| format!("{}\nThe metadata of {} could not be parsed", err, base_name))
+        .and_then(|base_model| {
+            EdmxModel::parse(&raw_xml)
+                .map(|model| (base_model, model))
+                .map_err(|err| {
+                    format!(
+                        "{}\nThe metadata of service {} could not be parsed",
+                        err, service.id
+                    )
+                })
+        });
+
+    let diff = match models {
+        Ok((base_model, model)) => MetadataDiff::between(&base_model, &model),
+        Err(err_msg) => {
+            ui.state.error_msg = Some(err_msg);
+            log::error!("<--- compare_metadata() ERROR");
+            return Ok(build_http_response(
+                app_state,
+                ui,
+                StatusCode::UNPROCESSABLE_ENTITY,
+                tmpl,
+            ));
+        }
+    };
+
+    let compared_name = format!("{} in {}", service.id, profile_name);
+
+    let mut response = if qs.format.as_deref() == Some("json") {
+        HttpResponse::build(StatusCode::OK).json(json!({
+          "base": base_name,
+          "compared": compared_name,
+          "diff": diff
+        }))
+    } else {
+        let json_url = format!(
+            "./diff?{}&format=json",
+            req.query_string()
+                .split('&')
+                .filter(|param| !param.starts_with("format="))
+                .collect::<Vec<_>>()
+                .join("&")
+        );
+        let response_body = tmpl
+            .render(
+                "diff.html",
+                &json!({
+                  "hostName": client.profile().hostname,
+                  "profile": profile_name,
+                  "serviceId": service.id,
+                  "base": base_name,
+                  "compared": compared_name,
+                  "diff": diff,
+                  "jsonUrl": json_url
+                }),
+            )
+            .map_err(|err| error::ErrorInternalServerError(format!("Template error\n{}", err)))?;
+
+        HttpResponse::build(StatusCode::OK)
+            .content_type("text/html; charset=utf-8")
+            .body(response_body)
+    };
+
+    app_state.ui_sessions.save(ui, &mut response);
+    log::info!("<--- compare_metadata()");
+
+    Ok(response)
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+// Utility functions
+// ---------------------------------------------------------------------------------------------------------------------
+/// Look up the catalog client for the requested profile, and make that profile the browser's current selection
+///
+/// Without a profile name, the browser stays with its current selection, or starts with the default profile
+fn select_profile(
+    app_state: &AppState,
+    ui: &mut UiState,
+    name: Option<&str>,
+) -> Result<Arc<CatalogClient>, String> {
+    let profile = app_state.config.profile(name.or(ui.profile.as_deref()))?;
+
+    // Catalogs and services belong to the previously selected system
+    if ui.profile.as_deref() != Some(&profile.name) {
+        ui.catalog_list = None;
+        ui.service_list = None;
+        ui.service_details = None;
+        ui.pending_cache_clear = None;
+        ui.annotation_list = None;
+        ui.annotation_usage = None;
+        ui.vocabulary_list = None;
+        ui.tag_list = None;
+        ui.probe_results = None;
+        ui.profile = Some(profile.name.clone());
+    }
+
+    Ok(app_state.clients[&profile.name].clone())
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// List services by ID in the service dropdown, with the first one selected
+fn set_service_list(ui: &mut UiState, services: Vec<Service>, catalog: Option<&str>) {
+    let mut service_list: Vec<(String, String)> = services
+        .into_iter()
+        .map(|srv| (srv.id, srv.metadata_url))
+        .collect();
+
+    service_list.sort_by(|a, b| a.0.cmp(&b.0));
+
+    ui.last_srv = service_list.first().map(|srv| srv.1.clone());
+    ui.service_list = Some(service_list);
+    ui.service_catalog = catalog.map(String::from);
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// The service IDs in a comma separated list
+fn service_id_list(ids: &str) -> Vec<&str> {
+    ids.split(',')
+        .map(str::trim)
+        .filter(|id| !id.is_empty())
+        .collect()
+}
+
+/// Read the services with the given IDs, a few at a time
+async fn services_by_id(
+    client: &CatalogClient,
+    ids: &[&str],
+) -> Result<Vec<Service>, CatalogError> {
+    log::info!("     Fetching {} services", ids.len());
+    stream::iter(ids.iter())
+        .map(|id| client.service(id))
+        .buffered(DEFAULT_CONCURRENCY)
+        .try_collect::<Vec<Service>>()
+        .await
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// The status with which to report a failed catalog request: the Gateway's own status, if it responded
+fn error_status(err: &CatalogError) -> StatusCode {
+    match err {
+        CatalogError::NotPermitted(_) => StatusCode::FORBIDDEN,
+        err => match err.status() {
+            Some(status) => {
+                StatusCode::from_u16(status.as_u16()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR)
+            }
+            None => StatusCode::BAD_GATEWAY,
+        },
+    }
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// Fetch a service and its metadata, or the status and message with which to report the failure
+async fn service_metadata(
+    client: &CatalogClient,
+    service_id: &str,
+) -> Result<(Service, String), (StatusCode, String)> {
+    let metadata = match client.service(service_id).await {
+        Ok(service) => client
+            .metadata(&service)
+            .await
+            .map(|raw_xml| (service, raw_xml)),
+        Err(err) => Err(err),
+    };
+
+    metadata.map_err(|err| {
+        (
+            error_status(&err),
+            format!(
+                "{}\nThe metadata of service {} could not be read",
+                err, service_id
+            ),
+        )
+    })
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// List vocabularies by namespace, showing which have been stored locally
+fn set_vocabulary_list(ui: &mut UiState, store: &VocabularyStore, vocabularies: &[Vocabulary]) {
+    let mut vocabulary_list: Vec<VocabularyRow> = vocabularies
+        .iter()
+        .map(|vocabulary| VocabularyRow::new(vocabulary, store))
+        .collect();
+
+    vocabulary_list.sort_by(|a, b| a.namespace.cmp(&b.namespace));
+    ui.vocabulary_list = Some(vocabulary_list);
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// The name under which a browser saves a downloaded annotation or vocabulary file
+fn download_file_name(technical_name: &str, version: &str) -> String {
+    format!(
+        "{}_{}.xml",
+        safe_file_name(technical_name),
+        safe_file_name(version)
+    )
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// Template formatter for values passed in a link's query string
+fn url_encode(value: &Value, output: &mut String) -> tinytemplate::error::Result<()> {
+    match value {
+        Value::String(text) => {
+            output.push_str(&utf8_percent_encode(text, NON_ALPHANUMERIC).to_string())
+        }
+        other => output.push_str(&other.to_string()),
+    }
+
+    Ok(())
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+/// Render the index page from the requesting browser's UI state, then store that state
+fn build_http_response<'template>(
+    app_state: web::Data<AppState>,
+    mut ui: UiSession,
+    status_code: StatusCode,
+    tmpl: web::Data<TinyTemplate<'template>>,
+) -> HttpResponse {
+    let profile_name = ui
+        .state
+        .profile
+        .clone()
+        .unwrap_or_else(|| app_state.config.default_profile.clone());
+    let (host_name, allow_cache_clear) = app_state
+        .config
+        .profile(Some(&profile_name))
+        .map(|p| (p.hostname.clone(), p.allow_cache_clear))
+        .unwrap_or_default();
+    let profile_list: Vec<(String, String)> = app_state
+        .config
+        .profiles
+        .iter()
+        .map(|p| (p.name.clone(), p.hostname.clone()))
+        .collect();
+
+    let response_body = tmpl
+        .render(
+            "index.html",
+            &json!({
+              "hostName": host_name,
+              "profile": profile_name,
+              "profileList": profile_list,
+              "catalogList": ui.state.catalog_list.as_deref().unwrap_or_default(),
+              "serviceList": ui.state.service_list,
+              "serviceCatalog": ui.state.service_catalog,
+              "serviceSearch": ui.state.service_search,
+              "serviceCount": ui.state.service_count,
+              "serviceDetails": ui.state.service_details,
+              "expandServices": ui.state.service_details.is_some(),
+              "bestMatchForm": ui.state.best_match_form,
+              "bestMatch": ui.state.best_match,
+              "annotationList": ui.state.annotation_list,
+              "annotationUsage": ui.state.annotation_usage,
+              "vocabularyList": ui.state.vocabulary_list,
+              "tagList": ui.state.tag_list,
+              "probeResults": ui.state.probe_results,
+              "vocabularyDir": app_state.vocabularies.dir().display().to_string(),
+              "allowCacheClear": allow_cache_clear,
+              "pendingCacheClear": ui.state.pending_cache_clear,
+              "infoMsg": ui.state.info_msg.take(),
+              "refreshedMetadata": ui.state.refreshed_metadata.take(),
+              "projectDir": app_state.project.as_ref().map(|p| p.root().display().to_string()),
+              "metadataSave": ui.state.metadata_save.take().map(|(service_id, summary)| json!({
+                "serviceId": service_id,
+                "notOverwritten": summary.outcome == SaveOutcome::NotOverwritten,
+                "summary": summary
+              })),
+              "errMsg": ui.state.error_msg,
+              "lastSrv": ui.state.last_srv
+            }),
+        )
+        .map_err(|err| error::ErrorInternalServerError(format!("Template error\n{}", err)))
+        .unwrap();
+
+    let mut response = HttpResponse::build(status_code)
+        .content_type("text/html; charset=utf-8")
+        .body(response_body);
+
+    app_state.ui_sessions.save(ui, &mut response);
+    response
+}
@@ -0,0 +1,111 @@
+use crate::{
+    catalogservice::Service,
+    client::{CatalogClient, CatalogError},
+};
+use chrono::{NaiveDateTime, Utc};
+use futures_util::{stream, StreamExt};
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::time::Instant;
+
+/// The number of services probed at the same time, unless the caller chooses otherwise
+pub static DEFAULT_CONCURRENCY: usize = 8;
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// What happened when a service's service document was requested
+///
+/// A service can be listed in the catalog even though it has only been partially defined, or has not been activated
+/// in the Gateway hub.  Such a service only shows itself when it is called.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum ProbeStatus {
+    #[serde(rename = "OK")]
+    Ok,
+    Unauthorized,
+    Forbidden,
+    /// The Gateway does not know the service, usually because it has not been activated
+    #[serde(rename = "Not activated")]
+    NotActivated,
+    #[serde(rename = "Server error")]
+    ServerError,
+    /// Any other HTTP status, or a service URL that does not belong to the SAP system
+    Failed,
+    /// No HTTP response was received
+    Unreachable,
+}
+
+impl From<StatusCode> for ProbeStatus {
+    fn from(status: StatusCode) -> Self {
+        match status {
+            status if status.is_success() => ProbeStatus::Ok,
+            StatusCode::UNAUTHORIZED => ProbeStatus::Unauthorized,
+            StatusCode::FORBIDDEN => ProbeStatus::Forbidden,
+            StatusCode::NOT_FOUND => ProbeStatus::NotActivated,
+            status if status.is_server_error() => ProbeStatus::ServerError,
+            _ => ProbeStatus::Failed,
+        }
+    }
+}
+
+/// The outcome of probing one service
+#[derive(Clone, Debug, Serialize)]
+pub struct ProbeResult {
+    pub service_id: String,
+    pub service_url: String,
+    pub status: ProbeStatus,
+    pub http_status: Option<u16>,
+    /// The message from the Gateway's OData error, or the reason no response was received
+    pub message: Option<String>,
+    /// When the request was sent (UTC)
+    pub checked_at: NaiveDateTime,
+    pub elapsed_ms: u64,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Request the service document of a service and classify the response
+pub async fn probe_service(client: &CatalogClient, service: &Service) -> ProbeResult {
+    let checked_at = Utc::now().naive_utc();
+    let started = Instant::now();
+    let outcome = client.service_document_at(&service.service_url).await;
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+
+    let (status, http_status, message) = match outcome {
+        Ok((status, _)) => (ProbeStatus::from(status), Some(status.as_u16()), None),
+        Err(CatalogError::Status { status, message }) => (
+            ProbeStatus::from(status),
+            Some(status.as_u16()),
+            Some(message),
+        ),
+        Err(err @ CatalogError::NotPermitted(_)) => {
+            (ProbeStatus::Failed, None, Some(err.to_string()))
+        }
+        Err(err) => (ProbeStatus::Unreachable, None, Some(err.to_string())),
+    };
+
+    ProbeResult {
+        service_id: service.id.clone(),
+        service_url: service.service_url.clone(),
+        status,
+        http_status,
+        message,
+        checked_at,
+        elapsed_ms,
+    }
+}
+
+/// Probe every service, with no more than `concurrency` requests in flight at once.  The results are returned in the
+/// same order as the services.
+pub async fn probe_services(
+    client: &CatalogClient,
+    services: &[Service],
+    concurrency: usize,
+) -> Vec<ProbeResult> {
+    stream::iter(services)
+        .map(|service| probe_service(client, service))
+        .buffered(concurrency.max(1))
+        .collect()
+        .await
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
@@ -0,0 +1,171 @@
+use super::*;
+use crate::config::{AuthConfig, PayloadFormat, Profile};
+use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+use std::{
+    fs,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Counts the requests the mock Gateway is handling at any one time
+#[derive(Default)]
+struct InFlight {
+    current: AtomicUsize,
+    max: AtomicUsize,
+}
+
+/// Start a Gateway whose services respond according to their names, and a client for it
+fn start_mock_gateway() -> (String, CatalogClient, Arc<InFlight>) {
+    let in_flight = Arc::new(InFlight::default());
+    let app_in_flight = in_flight.clone();
+
+    let server = HttpServer::new(move || {
+        let in_flight = app_in_flight.clone();
+
+        App::new().default_service(web::to(move |req: HttpRequest| {
+            let in_flight = in_flight.clone();
+
+            async move {
+                let current = in_flight.current.fetch_add(1, Ordering::SeqCst) + 1;
+                in_flight.max.fetch_max(current, Ordering::SeqCst);
+                actix_rt::time::sleep(Duration::from_millis(20)).await;
+                in_flight.current.fetch_sub(1, Ordering::SeqCst);
+
+                let odata_error = fs::read("./test_data/ODataError.xml").unwrap();
+
+                match req.path() {
+                    path if path.starts_with("/sap/opu/odata/sap/OK_SRV") => HttpResponse::Ok()
+                        .content_type("application/xml")
+                        .body(fs::read("./test_data/CatalogService.xml").unwrap()),
+                    path if path.starts_with("/sap/opu/odata/sap/EMPTY_SRV") => {
+                        HttpResponse::NoContent().finish()
+                    }
+                    path if path.starts_with("/sap/opu/odata/sap/FORBIDDEN_SRV") => {
+                        HttpResponse::Forbidden().finish()
+                    }
+                    path if path.starts_with("/sap/opu/odata/sap/BROKEN_SRV") => {
+                        HttpResponse::InternalServerError()
+                            .content_type("application/xml")
+                            .body(odata_error)
+                    }
+                    _ => HttpResponse::NotFound()
+                        .content_type("application/xml")
+                        .body(odata_error),
+                }
+            }
+        }))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+
+    let base_url = format!("http://{}", server.addrs()[0]);
+    actix_rt::spawn(server.run());
+
+    let client = CatalogClient::new(&Profile {
+        name: "TEST".to_owned(),
+        hostname: "127.0.0.1".to_owned(),
+        port: None,
+        client: None,
+        auth: AuthConfig::Basic {
+            user: "user".to_owned(),
+            password: "secret".to_owned(),
+        },
+        ca_bundle: None,
+        catalog_path: "/sap/opu/odata/iwfnd/catalogservice;v=2".to_owned(),
+        format: PayloadFormat::Atom,
+        allow_cache_clear: false,
+    })
+    .unwrap()
+    .with_service_url(catalog_service_url(&base_url));
+
+    (base_url, client, in_flight)
+}
+
+fn catalog_service_url(base_url: &str) -> String {
+    format!("{}/sap/opu/odata/iwfnd/catalogservice;v=2/", base_url)
+}
+
+fn service(base_url: &str, name: &str) -> Service {
+    Service {
+        id: format!("{}_0001", name),
+        service_url: format!("{}/sap/opu/odata/sap/{}", base_url, name),
+        ..Default::default()
+    }
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[actix_web::test]
+pub async fn should_classify_service_responses() {
+    let (base_url, client, _) = start_mock_gateway();
+    let services: Vec<Service> = [
+        "OK_SRV",
+        "FORBIDDEN_SRV",
+        "ZCUSTOM_SRV",
+        "BROKEN_SRV",
+        "EMPTY_SRV",
+    ]
+    .iter()
+    .map(|name| service(&base_url, name))
+    .collect();
+
+    let results = probe_services(&client, &services, DEFAULT_CONCURRENCY).await;
+    let statuses: Vec<ProbeStatus> = results.iter().map(|r| r.status).collect();
+
+    assert_eq!(
+        statuses,
+        [
+            ProbeStatus::Ok,
+            ProbeStatus::Forbidden,
+            ProbeStatus::NotActivated,
+            ProbeStatus::ServerError,
+            ProbeStatus::Ok
+        ]
+    );
+    assert_eq!(results[0].service_id, "OK_SRV_0001");
+    assert_eq!(results[0].http_status, Some(200));
+    assert_eq!(results[4].http_status, Some(204));
+    assert_eq!(results[2].http_status, Some(404));
+    assert!(results[3]
+        .message
+        .as_deref()
+        .unwrap()
+        .starts_with("No service found for namespace"));
+}
+
+#[actix_web::test]
+pub async fn should_limit_concurrent_requests() {
+    let (base_url, client, in_flight) = start_mock_gateway();
+    let services: Vec<Service> = (0..10).map(|_| service(&base_url, "OK_SRV")).collect();
+
+    let results = probe_services(&client, &services, 3).await;
+
+    assert_eq!(results.len(), 10);
+    assert!(results.iter().all(|r| r.status == ProbeStatus::Ok));
+    assert!(in_flight.max.load(Ordering::SeqCst) <= 3);
+}
+
+#[actix_web::test]
+pub async fn should_report_unreachable_service() {
+    let (_, client, _) = start_mock_gateway();
+    // Nothing listens on port 1
+    let client = client.with_service_url(catalog_service_url("http://127.0.0.1:1"));
+    let result = probe_service(&client, &service("http://127.0.0.1:1", "OK_SRV")).await;
+
+    assert_eq!(result.status, ProbeStatus::Unreachable);
+    assert_eq!(result.http_status, None);
+    assert!(result.message.is_some());
+}
+
+#[actix_web::test]
+pub async fn should_not_probe_services_of_other_systems() {
+    let (_, client, in_flight) = start_mock_gateway();
+    let result = probe_service(&client, &service("http://example.com", "OK_SRV")).await;
+
+    assert_eq!(result.status, ProbeStatus::Failed);
+    assert_eq!(result.http_status, None);
+    assert_eq!(in_flight.max.load(Ordering::SeqCst), 0);
+}
@@ -0,0 +1,246 @@
+use quick_xml::{events::Event, Reader};
+use serde::Serialize;
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// `parse_sap_odata::parser::gen_src` reads service metadata from this directory of the project being built
+pub static ODATA_DIR: &str = "odata";
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// The name `gen_src` is given for a service, which is both the name of its metadata file (without `.xml`) and the name
+/// of the generated module, so it must be a valid Rust identifier
+///
+/// The technical service name is converted to lower case, as in `parse-sap-odata`'s own examples
+/// (`GWSAMPLE_BASIC` becomes `gwsample_basic`).  Characters not allowed in an identifier, such as the slashes of a
+/// namespace, become underscores, and versions after the first are added as a suffix (`zpdcds_srv_v2`).
+pub fn metadata_file_name(technical_service_name: &str, technical_service_version: i16) -> String {
+    let mut name: String = technical_service_name
+        .trim_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+
+    if technical_service_version > 1 {
+        name.push_str(&format!("_v{}", technical_service_version));
+    }
+
+    name
+}
+
+/// The `Namespace` of the first `<Schema>` in a metadata document, which is the second argument of `gen_src`
+pub fn schema_namespace(raw_xml: &str) -> Option<String> {
+    let mut reader = Reader::from_str(raw_xml);
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(start)) | Ok(Event::Empty(start))
+                if start.local_name().as_ref() == b"Schema" =>
+            {
+                return start
+                    .try_get_attribute("Namespace")
+                    .ok()
+                    .flatten()
+                    .and_then(|attr| attr.unescape_value().ok())
+                    .map(|value| value.into_owned());
+            }
+            Ok(Event::Eof) | Err(_) => return None,
+            Ok(_) => {}
+        }
+    }
+}
+
+/// The `Name` attributes of every element with the given local name
+fn element_names(raw_xml: &str, element: &str) -> BTreeSet<String> {
+    let mut reader = Reader::from_str(raw_xml);
+    let mut names = BTreeSet::new();
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(start)) | Ok(Event::Empty(start))
+                if start.local_name().as_ref() == element.as_bytes() =>
+            {
+                if let Some(name) = start
+                    .try_get_attribute("Name")
+                    .ok()
+                    .flatten()
+                    .and_then(|attr| attr.unescape_value().ok())
+                {
+                    names.insert(name.into_owned());
+                }
+            }
+            Ok(Event::Eof) | Err(_) => return names,
+            Ok(_) => {}
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// What saving a metadata file did
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum SaveOutcome {
+    Created,
+    Replaced,
+    Unchanged,
+    /// The file exists with different content, and overwriting it was not allowed
+    NotOverwritten,
+}
+
+/// How a new metadata document differs from the one already saved
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct MetadataChanges {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    pub entity_types_added: Vec<String>,
+    pub entity_types_removed: Vec<String>,
+    pub entity_sets_added: Vec<String>,
+    pub entity_sets_removed: Vec<String>,
+}
+
+impl MetadataChanges {
+    pub fn between(old_xml: &str, new_xml: &str) -> MetadataChanges {
+        let old_lines: BTreeSet<&str> = old_xml.lines().map(str::trim).collect();
+        let new_lines: BTreeSet<&str> = new_xml.lines().map(str::trim).collect();
+
+        let difference = |element: &str| {
+            let old_names = element_names(old_xml, element);
+            let new_names = element_names(new_xml, element);
+
+            (
+                new_names.difference(&old_names).cloned().collect(),
+                old_names.difference(&new_names).cloned().collect(),
+            )
+        };
+        let (entity_types_added, entity_types_removed) = difference("EntityType");
+        let (entity_sets_added, entity_sets_removed) = difference("EntitySet");
+
+        MetadataChanges {
+            lines_added: new_lines.difference(&old_lines).count(),
+            lines_removed: old_lines.difference(&new_lines).count(),
+            entity_types_added,
+            entity_types_removed,
+            entity_sets_added,
+            entity_sets_removed,
+        }
+    }
+}
+
+/// The result of saving a service's metadata into a project
+#[derive(Clone, Debug, Serialize)]
+pub struct SaveSummary {
+    pub path: PathBuf,
+    /// The first argument of `gen_src`
+    pub file_name: String,
+    /// The second argument of `gen_src`
+    pub namespace: Option<String>,
+    pub outcome: SaveOutcome,
+    /// Compared with the file that was there before.  `None` if there was no such file, or it was the same.
+    pub changes: Option<MetadataChanges>,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// A Rust project whose build script calls `parse_sap_odata::parser::gen_src`
+#[derive(Debug, Clone)]
+pub struct ProjectDir {
+    root: PathBuf,
+}
+
+impl ProjectDir {
+    pub fn new(root: impl Into<PathBuf>) -> ProjectDir {
+        ProjectDir { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The path of the metadata file that `gen_src(file_name, ...)` reads
+    pub fn metadata_path(&self, file_name: &str) -> PathBuf {
+        self.root.join(ODATA_DIR).join(format!("{}.xml", file_name))
+    }
+
+    /// Save a service's metadata as `odata/<file_name>.xml`
+    ///
+    /// An existing file with different content is only replaced if `overwrite` is set; either way, the summary
+    /// describes how the new metadata differs from it.
+    pub fn save_metadata(
+        &self,
+        file_name: &str,
+        raw_xml: &str,
+        overwrite: bool,
+    ) -> Result<SaveSummary, String> {
+        let path = self.metadata_path(file_name);
+        let write_error =
+            |err: io::Error| format!("Unable to write metadata file {}: {}", path.display(), err);
+
+        let existing = match fs::read_to_string(&path) {
+            Ok(existing) => Some(existing),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => {
+                return Err(format!(
+                    "Unable to read metadata file {}: {}",
+                    path.display(),
+                    err
+                ))
+            }
+        };
+
+        let outcome = match &existing {
+            None => SaveOutcome::Created,
+            Some(existing) if existing == raw_xml => SaveOutcome::Unchanged,
+            Some(_) if overwrite => SaveOutcome::Replaced,
+            Some(_) => SaveOutcome::NotOverwritten,
+        };
+
+        if matches!(outcome, SaveOutcome::Created | SaveOutcome::Replaced) {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(write_error)?;
+            }
+            fs::write(&path, raw_xml).map_err(write_error)?;
+        }
+
+        Ok(SaveSummary {
+            file_name: file_name.to_owned(),
+            namespace: schema_namespace(raw_xml),
+            outcome,
+            changes: existing
+                .filter(|existing| existing != raw_xml)
+                .map(|existing| MetadataChanges::between(&existing, raw_xml)),
+            path,
+        })
+    }
+
+    /// Read the metadata saved as `odata/<file_name>.xml`
+    ///
+    /// Only names that `metadata_file_name` could have produced are accepted, so no file outside the project's
+    /// `odata` directory can be read.
+    pub fn read_metadata(&self, file_name: &str) -> Result<String, String> {
+        if file_name.is_empty()
+            || !file_name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(format!("{} is not the name of a metadata file", file_name));
+        }
+
+        let path = self.metadata_path(file_name);
+        fs::read_to_string(&path)
+            .map_err(|err| format!("Unable to read metadata file {}: {}", path.display(), err))
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
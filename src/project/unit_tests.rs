@@ -0,0 +1,84 @@
+use super::*;
+
+static CATALOG_METADATA: &str = "./odata/catalogservice.xml";
+
+fn temp_project(name: &str) -> ProjectDir {
+    let dir = std::env::temp_dir().join(format!("{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    ProjectDir::new(dir)
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_name_metadata_file_as_gen_src_expects() {
+    assert_eq!(metadata_file_name("GWSAMPLE_BASIC", 1), "gwsample_basic");
+    assert_eq!(metadata_file_name("ZPDCDS_SRV", 2), "zpdcds_srv_v2");
+    assert_eq!(
+        metadata_file_name("/IWBEP/GWSAMPLE_BASIC", 1),
+        "iwbep_gwsample_basic"
+    );
+}
+
+#[test]
+pub fn should_read_schema_namespace() {
+    let raw_xml = fs::read_to_string(CATALOG_METADATA).unwrap();
+    assert_eq!(
+        schema_namespace(&raw_xml).as_deref(),
+        Some("catalogservice")
+    );
+    assert_eq!(schema_namespace("<edmx:Edmx/>"), None);
+}
+
+#[test]
+pub fn should_protect_changed_metadata_from_overwrite() {
+    let project = temp_project("project-overwrite");
+    let raw_xml = fs::read_to_string(CATALOG_METADATA).unwrap();
+
+    let summary = project
+        .save_metadata("catalogservice", &raw_xml, false)
+        .unwrap();
+    assert_eq!(summary.outcome, SaveOutcome::Created);
+    assert_eq!(summary.namespace.as_deref(), Some("catalogservice"));
+    assert!(summary.path.ends_with("odata/catalogservice.xml"));
+    assert!(summary.changes.is_none());
+
+    let summary = project
+        .save_metadata("catalogservice", &raw_xml, false)
+        .unwrap();
+    assert_eq!(summary.outcome, SaveOutcome::Unchanged);
+    assert!(summary.changes.is_none());
+
+    // Rename the Tag entity type and its entity set
+    let changed_xml = raw_xml
+        .replace("<EntityType Name=\"Tag\"", "<EntityType Name=\"Label\"")
+        .replace(
+            "<EntitySet Name=\"TagCollection\"",
+            "<EntitySet Name=\"Labels\"",
+        );
+
+    let summary = project
+        .save_metadata("catalogservice", &changed_xml, false)
+        .unwrap();
+    assert_eq!(summary.outcome, SaveOutcome::NotOverwritten);
+    assert_eq!(fs::read_to_string(&summary.path).unwrap(), raw_xml);
+
+    let changes = summary.changes.unwrap();
+    assert_eq!(changes.entity_types_added, ["Label"]);
+    assert_eq!(changes.entity_types_removed, ["Tag"]);
+    assert_eq!(changes.entity_sets_added, ["Labels"]);
+    assert_eq!(changes.entity_sets_removed, ["TagCollection"]);
+    assert_eq!((changes.lines_added, changes.lines_removed), (2, 2));
+
+    let summary = project
+        .save_metadata("catalogservice", &changed_xml, true)
+        .unwrap();
+    assert_eq!(summary.outcome, SaveOutcome::Replaced);
+    assert_eq!(fs::read_to_string(&summary.path).unwrap(), changed_xml);
+    assert_eq!(
+        project.read_metadata("catalogservice").unwrap(),
+        changed_xml
+    );
+    assert!(project.read_metadata("../odata/catalogservice").is_err());
+
+    fs::remove_dir_all(project.root()).unwrap();
+}
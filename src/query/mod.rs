@@ -0,0 +1,391 @@
+use chrono::NaiveDateTime;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use std::fmt;
+use uuid::Uuid;
+
+/// Characters that must be percent-encoded in a resource path segment
+static PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+/// Characters that must be percent-encoded in the value of a query option
+static QUERY_VALUE: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'&')
+    .add(b'+')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`')
+    .add(b'{')
+    .add(b'}');
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// An OData V2 literal, as used in key predicates, filter expressions and function import parameters
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Boolean(bool),
+    Int32(i32),
+    Int64(i64),
+    String(String),
+    DateTime(NaiveDateTime),
+    Guid(Uuid),
+}
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Null => write!(f, "null"),
+            Literal::Boolean(value) => write!(f, "{}", value),
+            Literal::Int32(value) => write!(f, "{}", value),
+            Literal::Int64(value) => write!(f, "{}L", value),
+            // A single quote inside a string literal is written as two single quotes
+            Literal::String(value) => write!(f, "'{}'", value.replace('\'', "''")),
+            Literal::DateTime(value) => {
+                write!(f, "datetime'{}'", value.format("%Y-%m-%dT%H:%M:%S"))
+            }
+            Literal::Guid(value) => write!(f, "guid'{}'", value),
+        }
+    }
+}
+
+impl From<&str> for Literal {
+    fn from(value: &str) -> Self {
+        Literal::String(value.to_owned())
+    }
+}
+
+impl From<String> for Literal {
+    fn from(value: String) -> Self {
+        Literal::String(value)
+    }
+}
+
+impl From<bool> for Literal {
+    fn from(value: bool) -> Self {
+        Literal::Boolean(value)
+    }
+}
+
+impl From<i16> for Literal {
+    fn from(value: i16) -> Self {
+        Literal::Int32(value.into())
+    }
+}
+
+impl From<i32> for Literal {
+    fn from(value: i32) -> Self {
+        Literal::Int32(value)
+    }
+}
+
+impl From<i64> for Literal {
+    fn from(value: i64) -> Self {
+        Literal::Int64(value)
+    }
+}
+
+impl From<NaiveDateTime> for Literal {
+    fn from(value: NaiveDateTime) -> Self {
+        Literal::DateTime(value)
+    }
+}
+
+impl From<Uuid> for Literal {
+    fn from(value: Uuid) -> Self {
+        Literal::Guid(value)
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// A `$filter` expression
+///
+/// Values are always written as escaped literals, so a filter cannot be broken by the text it searches for
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter(String);
+
+impl Filter {
+    pub fn eq(property: &str, value: impl Into<Literal>) -> Filter {
+        Filter::compare(property, "eq", value.into())
+    }
+
+    pub fn ne(property: &str, value: impl Into<Literal>) -> Filter {
+        Filter::compare(property, "ne", value.into())
+    }
+
+    pub fn gt(property: &str, value: impl Into<Literal>) -> Filter {
+        Filter::compare(property, "gt", value.into())
+    }
+
+    pub fn ge(property: &str, value: impl Into<Literal>) -> Filter {
+        Filter::compare(property, "ge", value.into())
+    }
+
+    pub fn lt(property: &str, value: impl Into<Literal>) -> Filter {
+        Filter::compare(property, "lt", value.into())
+    }
+
+    pub fn le(property: &str, value: impl Into<Literal>) -> Filter {
+        Filter::compare(property, "le", value.into())
+    }
+
+    /// The property contains the text
+    pub fn substring_of(property: &str, text: &str) -> Filter {
+        Filter(format!("substringof({},{})", Literal::from(text), property))
+    }
+
+    pub fn starts_with(property: &str, text: &str) -> Filter {
+        Filter(format!("startswith({},{})", property, Literal::from(text)))
+    }
+
+    pub fn ends_with(property: &str, text: &str) -> Filter {
+        Filter(format!("endswith({},{})", property, Literal::from(text)))
+    }
+
+    pub fn and(self, other: Filter) -> Filter {
+        Filter(format!("({}) and ({})", self.0, other.0))
+    }
+
+    pub fn or(self, other: Filter) -> Filter {
+        Filter(format!("({}) or ({})", self.0, other.0))
+    }
+
+    #[allow(clippy::should_implement_trait)]
+    pub fn not(self) -> Filter {
+        Filter(format!("not ({})", self.0))
+    }
+
+    fn compare(property: &str, operator: &str, value: Literal) -> Filter {
+        Filter(format!("{} {} {}", property, operator, value))
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    Ascending,
+    Descending,
+}
+
+/// The resource path and query options of an OData request, relative to the service root
+///
+/// ```
+/// use read_sap_odata_catalog::query::{Filter, Query};
+///
+/// let query = Query::entity_set("CatalogCollection")
+///     .key("ES5")
+///     .navigate("Services")
+///     .filter(Filter::eq("Author", "SAP"))
+///     .top(10);
+///
+/// assert_eq!(
+///     query.to_string(),
+///     "CatalogCollection('ES5')/Services?$filter=Author%20eq%20'SAP'&$top=10"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    resource_path: String,
+    parameters: Vec<(String, String)>,
+    filter: Option<Filter>,
+    select: Vec<String>,
+    order_by: Vec<String>,
+    expand: Vec<String>,
+    top: Option<usize>,
+    skip: Option<usize>,
+    inline_count: bool,
+    search: Option<String>,
+}
+
+impl Query {
+    pub fn entity_set(name: &str) -> Query {
+        Query {
+            resource_path: utf8_percent_encode(name, PATH_SEGMENT).to_string(),
+            ..Query::default()
+        }
+    }
+
+    /// Call a function import, whose parameters are then added with [`Query::parameter`]
+    pub fn function_import(name: &str) -> Query {
+        Query::entity_set(name)
+    }
+
+    /// Pass a parameter to a function import
+    pub fn parameter(mut self, name: &str, value: impl Into<Literal>) -> Query {
+        self.parameters
+            .push((name.to_owned(), value.into().to_string()));
+        self
+    }
+
+    /// Address a single entry by the value of its key property
+    pub fn key(mut self, value: impl Into<Literal>) -> Query {
+        let predicate = value.into().to_string();
+        self.resource_path.push_str(&format!(
+            "({})",
+            utf8_percent_encode(&predicate, PATH_SEGMENT)
+        ));
+        self
+    }
+
+    /// Address a single entry by the values of a composite key
+    pub fn keys(mut self, values: &[(&str, Literal)]) -> Query {
+        let predicate = values
+            .iter()
+            .map(|(property, value)| format!("{}={}", property, value))
+            .collect::<Vec<String>>()
+            .join(",");
+        self.resource_path.push_str(&format!(
+            "({})",
+            utf8_percent_encode(&predicate, PATH_SEGMENT)
+        ));
+        self
+    }
+
+    /// Follow a navigation property
+    pub fn navigate(mut self, navigation_property: &str) -> Query {
+        self.resource_path.push('/');
+        self.resource_path
+            .push_str(&utf8_percent_encode(navigation_property, PATH_SEGMENT).to_string());
+        self
+    }
+
+    /// Address the media resource (`$value`) of a media entity, such as an annotation file
+    pub fn media_resource(mut self) -> Query {
+        self.resource_path.push_str("/$value");
+        self
+    }
+
+    /// Restrict the entries read; calling this more than once combines the filters with `and`
+    pub fn filter(mut self, filter: Filter) -> Query {
+        self.filter = Some(match self.filter {
+            Some(existing) => existing.and(filter),
+            None => filter,
+        });
+        self
+    }
+
+    pub fn select(mut self, properties: &[&str]) -> Query {
+        self.select.extend(properties.iter().map(|p| p.to_string()));
+        self
+    }
+
+    pub fn order_by(mut self, property: &str, order: Order) -> Query {
+        self.order_by.push(match order {
+            Order::Ascending => format!("{} asc", property),
+            Order::Descending => format!("{} desc", property),
+        });
+        self
+    }
+
+    pub fn expand(mut self, navigation_property: &str) -> Query {
+        self.expand.push(navigation_property.to_owned());
+        self
+    }
+
+    /// Read at most this many entries, however the Gateway pages them
+    pub fn top(mut self, count: usize) -> Query {
+        self.top = Some(count);
+        self
+    }
+
+    pub fn skip(mut self, count: usize) -> Query {
+        self.skip = Some(count);
+        self
+    }
+
+    /// Ask the Gateway for the number of entries in the whole collection (`$inlinecount=allpages`)
+    pub fn inline_count(mut self) -> Query {
+        self.inline_count = true;
+        self
+    }
+
+    /// SAP's free text search (`search=`), supported by entity sets marked `sap:searchable`
+    pub fn search(mut self, text: &str) -> Query {
+        self.search = Some(text.to_owned());
+        self
+    }
+
+    /// The `$top` value, if any
+    pub fn max_entries(&self) -> Option<usize> {
+        self.top
+    }
+
+    /// The URL of this query, relative to a service root URL ending with `/`
+    pub fn to_url(&self, service_url: &str) -> String {
+        format!("{}{}", service_url, self)
+    }
+
+    /// Function import parameters come first, followed by the system query options
+    fn query_options(&self) -> Vec<(&str, String)> {
+        let mut options: Vec<(&str, String)> = self
+            .parameters
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.to_owned()))
+            .collect();
+
+        if let Some(filter) = &self.filter {
+            options.push(("$filter", filter.to_string()));
+        }
+        if !self.select.is_empty() {
+            options.push(("$select", self.select.join(",")));
+        }
+        if !self.order_by.is_empty() {
+            options.push(("$orderby", self.order_by.join(",")));
+        }
+        if !self.expand.is_empty() {
+            options.push(("$expand", self.expand.join(",")));
+        }
+        if let Some(top) = self.top {
+            options.push(("$top", top.to_string()));
+        }
+        if let Some(skip) = self.skip {
+            options.push(("$skip", skip.to_string()));
+        }
+        if self.inline_count {
+            options.push(("$inlinecount", "allpages".to_owned()));
+        }
+        if let Some(search) = &self.search {
+            options.push(("search", search.to_owned()));
+        }
+
+        options
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let options = self
+            .query_options()
+            .into_iter()
+            .map(|(name, value)| format!("{}={}", name, utf8_percent_encode(&value, QUERY_VALUE)))
+            .collect::<Vec<String>>();
+
+        if options.is_empty() {
+            write!(f, "{}", self.resource_path)
+        } else {
+            write!(f, "{}?{}", self.resource_path, options.join("&"))
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
@@ -0,0 +1,119 @@
+use super::*;
+use std::str::FromStr;
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_write_literals() {
+    assert_eq!(Literal::from("O'Neil").to_string(), "'O''Neil'");
+    assert_eq!(Literal::from(7i16).to_string(), "7");
+    assert_eq!(Literal::from(7i64).to_string(), "7L");
+    assert_eq!(Literal::from(true).to_string(), "true");
+    assert_eq!(
+        Literal::from(NaiveDateTime::from_str("2024-06-17T12:45:42").unwrap()).to_string(),
+        "datetime'2024-06-17T12:45:42'"
+    );
+    assert_eq!(
+        Literal::from(Uuid::from_str("0dc968d4-b56b-00f0-e006-4e99df3f6e6b").unwrap()).to_string(),
+        "guid'0dc968d4-b56b-00f0-e006-4e99df3f6e6b'"
+    );
+}
+
+#[test]
+pub fn should_escape_key_predicates() {
+    // Neither a quote nor a slash may end the key or start a new path segment
+    let query = Query::entity_set("CatalogCollection")
+        .key("Bob's catalog/v2?")
+        .navigate("Services");
+
+    assert_eq!(
+        query.to_string(),
+        "CatalogCollection('Bob''s%20catalog%2Fv2%3F')/Services"
+    );
+
+    let query = Query::entity_set("EntitySetCollection").keys(&[
+        ("ID", Literal::from("Products")),
+        ("SrvIdentifier", Literal::from("ZPDCDS_SRV_0001")),
+    ]);
+
+    assert_eq!(
+        query.to_string(),
+        "EntitySetCollection(ID='Products',SrvIdentifier='ZPDCDS_SRV_0001')"
+    );
+
+    let query = Query::entity_set("Annotations")
+        .keys(&[
+            ("TechnicalName", Literal::from("ZPDCDS_ANNO_MDL")),
+            ("Version", Literal::from("0001")),
+        ])
+        .media_resource();
+
+    assert_eq!(
+        query.to_string(),
+        "Annotations(TechnicalName='ZPDCDS_ANNO_MDL',Version='0001')/$value"
+    );
+}
+
+#[test]
+pub fn should_combine_filters() {
+    let filter = Filter::eq("Author", "SAP")
+        .and(Filter::substring_of("Title", "EPM").or(Filter::ge("TechnicalServiceVersion", 2i16)))
+        .and(Filter::eq("IsSapService", false).not());
+
+    assert_eq!(
+        filter.to_string(),
+        "((Author eq 'SAP') and ((substringof('EPM',Title)) or (TechnicalServiceVersion ge 2))) \
+         and (not (IsSapService eq false))"
+    );
+
+    // Filters added one at a time are combined with and
+    let query = Query::entity_set("ServiceCollection")
+        .filter(Filter::starts_with("ID", "Z"))
+        .filter(Filter::ends_with("ID", "_0001"));
+
+    assert_eq!(
+        query.to_string(),
+        "ServiceCollection?$filter=(startswith(ID,'Z'))%20and%20(endswith(ID,'_0001'))"
+    );
+}
+
+#[test]
+pub fn should_write_query_options() {
+    let query = Query::entity_set("ServiceCollection")
+        .filter(Filter::eq("Title", "A&B + C"))
+        .select(&["ID", "Title"])
+        .order_by("UpdatedDate", Order::Descending)
+        .order_by("ID", Order::Ascending)
+        .expand("EntitySets")
+        .top(20)
+        .skip(40)
+        .inline_count()
+        .search("sales order");
+
+    assert_eq!(
+        query.to_url("https://host/sap/opu/odata/iwfnd/catalogservice;v=2/"),
+        "https://host/sap/opu/odata/iwfnd/catalogservice;v=2/ServiceCollection\
+         ?$filter=Title%20eq%20'A%26B%20%2B%20C'\
+         &$select=ID,Title\
+         &$orderby=UpdatedDate%20desc,ID%20asc\
+         &$expand=EntitySets\
+         &$top=20\
+         &$skip=40\
+         &$inlinecount=allpages\
+         &search=sales%20order"
+    );
+    assert_eq!(query.max_entries(), Some(20));
+}
+
+#[test]
+pub fn should_pass_function_import_parameters() {
+    let query = Query::function_import("BestMatchingService")
+        .parameter("TechnicalServiceName", "ZPDCDS_SRV")
+        .parameter("TechnicalServiceVersionMin", 1i16)
+        .parameter("TechnicalServiceVersionMax", 2i16);
+
+    assert_eq!(
+        query.to_string(),
+        "BestMatchingService?TechnicalServiceName='ZPDCDS_SRV'\
+         &TechnicalServiceVersionMin=1&TechnicalServiceVersionMax=2"
+    );
+}
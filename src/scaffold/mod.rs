@@ -0,0 +1,409 @@
+use crate::{
+    catalogservice::Service,
+    client::{CatalogClient, CatalogError},
+    project::{metadata_file_name, schema_namespace, ODATA_DIR},
+};
+use parse_sap_odata::{
+    edmx::Edmx, parser::syntax_fragments::fragment_generators::gen_type_name,
+    utils::odata_name_to_rust_safe_name,
+};
+use std::{
+    collections::HashSet,
+    io::{Seek, Write},
+    str::FromStr,
+};
+use zip::{write::SimpleFileOptions, ZipWriter};
+
+/// The number of entries read from a service to serve as its test fixture
+pub static FIXTURE_ENTRIES: usize = 3;
+
+/// The crates needed by the code `parse-sap-odata` generates, at the versions this crate uses, plus an HTTP client
+/// for the sample code
+static DEPENDENCIES: &str = r#"chrono = { version = "0.4", features = ["serde"] }
+parse-sap-atom-feed = "0.2"
+quick-xml = { version = "0.35.0", features = ["serialize"] }
+reqwest = { version = "0.12", features = ["blocking"] }
+rust_decimal = { version = "1", features = ["serde-with-str"] }
+serde = { version = "1.0", features = ["derive"] }
+uuid = { version = "1.8", features = ["serde"] }
+"#;
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// The entity set used by the sample code and test of a scaffolded project, with the names `gen_src` gives it in Rust
+#[derive(Clone, Debug, PartialEq)]
+pub struct SampleEntitySet {
+    pub entity_set: String,
+    /// The enum generated for the entity container, with one variant per entity set
+    pub container_enum: String,
+    pub variant: String,
+    /// The struct generated for the entity set's entity type
+    pub entity_type: String,
+}
+
+/// The first entity set of the schema that can be read without a filter (or failing that, the first entity set), or
+/// `None` if the metadata cannot be parsed or has no entity sets
+pub fn sample_entity_set(raw_xml: &str, namespace: &str) -> Option<SampleEntitySet> {
+    let edmx = Edmx::from_str(raw_xml).ok()?;
+    let container = edmx
+        .data_services
+        .fetch_schema(namespace)?
+        .entity_container
+        .as_ref()?;
+    let entity_set = container
+        .entity_sets
+        .iter()
+        .find(|es| es.sap_annotations.is_addressable && !es.sap_annotations.requires_filter)
+        .or(container.entity_sets.first())?;
+    let entity_type = entity_set
+        .entity_type
+        .rsplit('.')
+        .next()
+        .unwrap_or(&entity_set.entity_type);
+
+    Some(SampleEntitySet {
+        entity_set: entity_set.name.clone(),
+        container_enum: gen_type_name(&container.name),
+        variant: gen_type_name(&entity_set.name),
+        entity_type: gen_type_name(entity_type),
+    })
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// One service of a scaffolded project
+#[derive(Clone, Debug)]
+pub struct ScaffoldService {
+    /// The name given to `gen_src`, which is also the name of the generated module
+    pub module_name: String,
+    pub namespace: String,
+    pub service_url: String,
+    pub raw_xml: String,
+    pub sample: Option<SampleEntitySet>,
+    /// The first few entries of the sample entity set, as read from the service
+    pub fixture: Option<String>,
+}
+
+impl ScaffoldService {
+    /// Read a service's metadata, and the entries of its sample entity set that become the test fixture
+    ///
+    /// A service whose entity set cannot be read still gets sample code, but no test.
+    pub async fn read(
+        client: &CatalogClient,
+        service: &Service,
+    ) -> Result<ScaffoldService, CatalogError> {
+        let raw_xml = client.metadata(service).await?;
+        let namespace = schema_namespace(&raw_xml).ok_or_else(|| {
+            CatalogError::Parse(format!(
+                "The metadata of service {} does not contain a schema",
+                service.id
+            ))
+        })?;
+        let sample = sample_entity_set(&raw_xml, &namespace);
+
+        let fixture = match &sample {
+            Some(sample) => match client
+                .entity_set_feed_at(&service.service_url, &sample.entity_set, FIXTURE_ENTRIES)
+                .await
+            {
+                Ok(feed) => Some(feed),
+                Err(err) => {
+                    log::warn!(
+                        "Unable to read entity set {} of service {}: {}",
+                        sample.entity_set,
+                        service.id,
+                        err
+                    );
+                    None
+                }
+            },
+            None => None,
+        };
+
+        Ok(ScaffoldService {
+            module_name: metadata_file_name(
+                &service.technical_service_name,
+                service.technical_service_version,
+            ),
+            namespace,
+            service_url: service.service_url.clone(),
+            raw_xml,
+            sample,
+            fixture,
+        })
+    }
+
+    fn fixture_path(&self, sample: &SampleEntitySet) -> String {
+        format!("test_data/{}/{}.xml", self.module_name, sample.entity_set)
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// A Cargo project that generates and uses the Rust bindings of one or more services
+///
+/// The project is laid out like this crate: `build.rs` calls `gen_src` for each service's metadata in `odata/`,
+/// `src/main.rs` includes the generated modules and reads a few entries from each service, and `src/unit_tests.rs`
+/// parses the entries saved in `test_data/`.
+#[derive(Clone, Debug)]
+pub struct ProjectScaffold {
+    name: String,
+    services: Vec<ScaffoldService>,
+}
+
+impl ProjectScaffold {
+    pub fn new(name: &str, services: Vec<ScaffoldService>) -> Result<ProjectScaffold, String> {
+        let valid_name = name.starts_with(|c: char| c.is_ascii_alphabetic())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+        if !valid_name {
+            return Err(format!("'{}' is not a valid Cargo package name", name));
+        }
+        if services.is_empty() {
+            return Err("A project needs at least one service".to_owned());
+        }
+
+        let mut module_names = HashSet::new();
+        if let Some(duplicate) = services
+            .iter()
+            .find(|srv| !module_names.insert(&srv.module_name))
+        {
+            return Err(format!(
+                "More than one service would generate module {}",
+                duplicate.module_name
+            ));
+        }
+
+        Ok(ProjectScaffold {
+            name: name.to_owned(),
+            services,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The path and content of every file in the project
+    pub fn files(&self) -> Vec<(String, String)> {
+        let mut files = vec![
+            ("Cargo.toml".to_owned(), self.manifest()),
+            ("build.rs".to_owned(), self.build_script()),
+            (".gitignore".to_owned(), "/target\n".to_owned()),
+            ("src/main.rs".to_owned(), self.main_rs()),
+        ];
+
+        for srv in self.services.iter() {
+            files.push((
+                format!("{}/{}.xml", ODATA_DIR, srv.module_name),
+                srv.raw_xml.clone(),
+            ));
+        }
+
+        if let Some(unit_tests) = self.unit_tests_rs() {
+            files.push(("src/unit_tests.rs".to_owned(), unit_tests));
+
+            for srv in self.services.iter() {
+                if let (Some(sample), Some(fixture)) = (&srv.sample, &srv.fixture) {
+                    files.push((srv.fixture_path(sample), fixture.clone()));
+                }
+            }
+        }
+
+        files
+    }
+
+    /// Write the project into a zip archive, inside a directory named after the project
+    pub fn write_zip<W: Write + Seek>(&self, writer: W) -> Result<W, String> {
+        let mut zip = ZipWriter::new(writer);
+
+        for (path, content) in self.files() {
+            let zip_error = |err: String| format!("Unable to add {} to zip archive: {}", path, err);
+
+            zip.start_file(
+                format!("{}/{}", self.name, path),
+                SimpleFileOptions::default(),
+            )
+            .map_err(|err| zip_error(err.to_string()))?;
+            zip.write_all(content.as_bytes())
+                .map_err(|err| zip_error(err.to_string()))?;
+        }
+
+        zip.finish()
+            .map_err(|err| format!("Unable to finish zip archive: {}", err))
+    }
+
+    // - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+    fn manifest(&self) -> String {
+        format!(
+            r#"[package]
+name = "{}"
+version = "0.1.0"
+edition = "2021"
+
+[build-dependencies]
+parse-sap-odata = {{ version = "1.3", features = ["parser"] }}
+
+[dependencies]
+{}"#,
+            self.name, DEPENDENCIES
+        )
+    }
+
+    fn build_script(&self) -> String {
+        let calls: String = self
+            .services
+            .iter()
+            .map(|srv| format!("    gen_src({:?}, {:?});\n", srv.module_name, srv.namespace))
+            .collect();
+
+        format!(
+            "use parse_sap_odata::parser::gen_src;\n\nfn main() {{\n{}}}\n",
+            calls
+        )
+    }
+
+    fn main_rs(&self) -> String {
+        let has_samples = self.services.iter().any(|srv| srv.sample.is_some());
+        let mut src = String::new();
+
+        if has_samples {
+            src.push_str("use parse_sap_atom_feed::atom::feed::Feed;\nuse std::{env, error::Error, str::FromStr};\n");
+        } else {
+            src.push_str("use std::error::Error;\n");
+        }
+        src.push('\n');
+
+        for srv in self.services.iter() {
+            src.push_str(&format!(
+                "include!(concat!(env!(\"OUT_DIR\"), {:?}));\n",
+                format!("/{}.rs", srv.module_name)
+            ));
+        }
+
+        if self.unit_tests_rs().is_some() {
+            src.push_str("\n#[cfg(test)]\npub mod unit_tests;\n");
+        }
+
+        if has_samples {
+            src.push_str(&format!(
+                r#"
+/// Read the first entries of an entity set, logging on with the user and password in `SAP_USER` and `SAP_PASSWORD`
+fn fetch_feed(service_url: &str, entity_set: &str) -> Result<String, Box<dyn Error>> {{
+    let response = reqwest::blocking::Client::new()
+        .get(format!("{{}}/{{}}?$top={}", service_url.trim_end_matches('/'), entity_set))
+        .basic_auth(env::var("SAP_USER")?, Some(env::var("SAP_PASSWORD")?))
+        .send()?
+        .error_for_status()?;
+
+    Ok(response.text()?)
+}}
+"#,
+                FIXTURE_ENTRIES
+            ));
+        }
+
+        src.push_str("\nfn main() -> Result<(), Box<dyn Error>> {\n");
+
+        for srv in self.services.iter() {
+            match &srv.sample {
+                Some(sample) => src.push_str(&format!(
+                    r#"    let xml = fetch_feed(
+        {url:?},
+        {module}::{container}::{variant}.variant_name(),
+    )?;
+    let feed = Feed::<{module}::{entity_type}>::from_str(&xml)?;
+    println!("{{}}: {{}} entries", feed.title, feed.entries.unwrap_or_default().len());
+
+"#,
+                    url = srv.service_url,
+                    module = srv.module_name,
+                    container = sample.container_enum,
+                    variant = sample.variant,
+                    entity_type = sample.entity_type,
+                )),
+                None => src.push_str(&format!(
+                    "    // Service {} has no entity sets\n\n",
+                    srv.module_name
+                )),
+            }
+        }
+
+        src.push_str("    Ok(())\n}\n");
+        src
+    }
+
+    /// A test for each service whose sample entries could be read, or `None` if there are no such services
+    fn unit_tests_rs(&self) -> Option<String> {
+        let tests: Vec<String> = self
+            .services
+            .iter()
+            .filter_map(|srv| match (&srv.sample, &srv.fixture) {
+                (Some(sample), Some(fixture)) => Some(format!(
+                    r#"
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_parse_{module}_{test_name}() {{
+    static ENTITY_SET_NAME: &str = "{entity_set}";
+
+    match fetch_xml_as_string("{fixture_file}") {{
+        Ok(xml) => {{
+            let feed = Feed::<{module}::{entity_type}>::from_str(&xml).unwrap();
+
+            assert_eq!(feed.title, ENTITY_SET_NAME);
+            assert_eq!(feed.entries.unwrap_or_default().len(), {entries});
+        }}
+        Err(err) => println!("XML test data was not in UTF8 format: {{}}", err),
+    }};
+}}
+"#,
+                    module = srv.module_name,
+                    test_name = odata_name_to_rust_safe_name(&sample.entity_set)
+                        .trim_start_matches("r#"),
+                    entity_set = sample.entity_set,
+                    fixture_file = srv
+                        .fixture_path(sample)
+                        .trim_start_matches("test_data/"),
+                    entity_type = sample.entity_type,
+                    entries = count_entries(fixture),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        if tests.is_empty() {
+            return None;
+        }
+
+        Some(format!(
+            r#"use std::{{
+    fs::File,
+    io::{{BufReader, Read}},
+    path::Path,
+    str::FromStr,
+    string::FromUtf8Error,
+}};
+use parse_sap_atom_feed::atom::feed::Feed;
+
+use crate::*;
+
+fn fetch_xml_as_string(filename: &str) -> Result<String, FromUtf8Error> {{
+    let mut xml_buffer: Vec<u8> = Vec::new();
+    let test_data = File::open(Path::new(&format!("./test_data/{{}}", filename))).unwrap();
+    let _file_size = BufReader::new(test_data).read_to_end(&mut xml_buffer);
+
+    String::from_utf8(xml_buffer)
+}}
+{}"#,
+            tests.concat()
+        ))
+    }
+}
+
+/// The number of entries in an Atom feed read without `$expand`
+fn count_entries(feed_xml: &str) -> usize {
+    feed_xml.matches("<entry>").count() + feed_xml.matches("<entry ").count()
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
@@ -0,0 +1,137 @@
+use super::*;
+use std::{fs, io::Cursor};
+use zip::ZipArchive;
+
+static CATALOG_METADATA: &str = "./odata/catalogservice.xml";
+
+fn catalog_service(fixture: Option<&str>) -> ScaffoldService {
+    let raw_xml = fs::read_to_string(CATALOG_METADATA).unwrap();
+
+    ScaffoldService {
+        module_name: "catalogservice".to_owned(),
+        namespace: "catalogservice".to_owned(),
+        service_url: "https://host/sap/opu/odata/iwfnd/catalogservice;v=2".to_owned(),
+        sample: sample_entity_set(&raw_xml, "catalogservice"),
+        raw_xml,
+        fixture: fixture.map(|file| fs::read_to_string(format!("./test_data/{}", file)).unwrap()),
+    }
+}
+
+fn file<'a>(files: &'a [(String, String)], path: &str) -> &'a str {
+    &files.iter().find(|(p, _)| p == path).unwrap().1
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_name_sample_entity_set_as_gen_src_does() {
+    let raw_xml = fs::read_to_string(CATALOG_METADATA).unwrap();
+
+    assert_eq!(
+        sample_entity_set(&raw_xml, "catalogservice"),
+        Some(SampleEntitySet {
+            entity_set: "Annotations".to_owned(),
+            container_enum: "CatalogserviceEntities".to_owned(),
+            variant: "Annotations".to_owned(),
+            entity_type: "Annotation".to_owned(),
+        })
+    );
+    assert_eq!(sample_entity_set(&raw_xml, "other"), None);
+}
+
+#[test]
+pub fn should_scaffold_project_files() {
+    let mut without_fixture = catalog_service(None);
+    without_fixture.module_name = "catalogservice_v2".to_owned();
+
+    let scaffold = ProjectScaffold::new(
+        "my_app",
+        vec![catalog_service(Some("Annotations.xml")), without_fixture],
+    )
+    .unwrap();
+    let files = scaffold.files();
+
+    let paths: Vec<&str> = files.iter().map(|(path, _)| path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "Cargo.toml",
+            "build.rs",
+            ".gitignore",
+            "src/main.rs",
+            "odata/catalogservice.xml",
+            "odata/catalogservice_v2.xml",
+            "src/unit_tests.rs",
+            "test_data/catalogservice/Annotations.xml"
+        ]
+    );
+
+    let manifest = file(&files, "Cargo.toml");
+    assert!(manifest.contains("name = \"my_app\""));
+    assert!(manifest.contains("parse-sap-odata = { version = \"1.3\", features = [\"parser\"] }"));
+    assert!(manifest.contains("parse-sap-atom-feed = \"0.2\""));
+
+    assert!(file(&files, "build.rs").contains(
+        "    gen_src(\"catalogservice\", \"catalogservice\");\n    gen_src(\"catalogservice_v2\", \"catalogservice\");\n"
+    ));
+
+    let main_rs = file(&files, "src/main.rs");
+    assert!(main_rs.contains("include!(concat!(env!(\"OUT_DIR\"), \"/catalogservice_v2.rs\"));"));
+    assert!(main_rs.contains("catalogservice::CatalogserviceEntities::Annotations.variant_name()"));
+    assert!(main_rs.contains("Feed::<catalogservice::Annotation>::from_str(&xml)?"));
+    assert!(main_rs.contains("pub mod unit_tests;"));
+    assert!(main_rs.contains(
+        ".get(format!(\"{}/{}?$top=3\", service_url.trim_end_matches('/'), entity_set))"
+    ));
+
+    // Only the service with a fixture is tested
+    let unit_tests = file(&files, "src/unit_tests.rs");
+    assert!(unit_tests.contains("pub fn should_parse_catalogservice_annotations()"));
+    assert!(unit_tests.contains("fetch_xml_as_string(\"catalogservice/Annotations.xml\")"));
+    assert!(unit_tests.contains("assert_eq!(feed.entries.unwrap_or_default().len(), 16);"));
+    assert!(!unit_tests.contains("catalogservice_v2"));
+}
+
+#[test]
+pub fn should_leave_out_tests_without_fixtures() {
+    let scaffold = ProjectScaffold::new("my-app", vec![catalog_service(None)]).unwrap();
+    let files = scaffold.files();
+
+    assert!(files.iter().all(|(path, _)| !path.starts_with("test_data")));
+    assert!(files.iter().all(|(path, _)| path != "src/unit_tests.rs"));
+    assert!(!file(&files, "src/main.rs").contains("unit_tests"));
+}
+
+#[test]
+pub fn should_escape_catalog_values_in_generated_code() {
+    let mut service = catalog_service(None);
+    service.namespace = r#"odd"name\space"#.to_owned();
+    service.service_url = r#"https://host/sap/opu/odata/sap/ZSRV"); panic!(""#.to_owned();
+
+    let scaffold = ProjectScaffold::new("my_app", vec![service]).unwrap();
+    let files = scaffold.files();
+
+    assert!(file(&files, "build.rs").contains(r#"gen_src("catalogservice", "odd\"name\\space");"#));
+    assert!(file(&files, "src/main.rs")
+        .contains(r#"        "https://host/sap/opu/odata/sap/ZSRV\"); panic!(\"","#));
+}
+
+#[test]
+pub fn should_reject_invalid_projects() {
+    assert!(ProjectScaffold::new("1st app", vec![catalog_service(None)]).is_err());
+    assert!(ProjectScaffold::new("my_app", Vec::new()).is_err());
+    assert_eq!(
+        ProjectScaffold::new("my_app", vec![catalog_service(None), catalog_service(None)])
+            .unwrap_err(),
+        "More than one service would generate module catalogservice"
+    );
+}
+
+#[test]
+pub fn should_write_project_into_zip() {
+    let scaffold = ProjectScaffold::new("my_app", vec![catalog_service(None)]).unwrap();
+    let zip = scaffold.write_zip(Cursor::new(Vec::new())).unwrap();
+    let archive = ZipArchive::new(zip).unwrap();
+
+    assert!(archive.file_names().all(|name| name.starts_with("my_app/")));
+    assert_eq!(archive.len(), 5);
+}
@@ -0,0 +1,307 @@
+use crate::{
+    auth::Authenticator,
+    config::{AuthConfig, ClientIdentity, Profile},
+};
+use anyhow::anyhow;
+use reqwest::{
+    cookie::{CookieStore, Jar},
+    header::AUTHORIZATION,
+    Method, Response, StatusCode, Url,
+};
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+/// The Gateway identifies a logged-on user by these cookies.  `SAP_SESSIONID` is followed by the system id and client
+static SESSION_COOKIE_PREFIXES: [&str; 2] = ["SAP_SESSIONID", "MYSAPSSO2"];
+
+static SAP_CLIENT_PARAM: &str = "sap-client";
+
+static CSRF_HEADER: &str = "X-CSRF-Token";
+static CSRF_FETCH: &str = "Fetch";
+static CSRF_REQUIRED: &str = "Required";
+static CSRF_FAILURE: &str = "CSRF token validation failed";
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// A logon session with one SAP system, shared by every request sent to that system
+///
+/// The cookies set by the Gateway at logon are sent back on later requests, so credentials are only sent when there is
+/// no session yet, or when the Gateway has ended it.  The HTTP client presents the profile's client certificate and
+/// trusts its CA bundle.
+///
+/// Requests that modify data carry an `X-CSRF-Token`.  The token is fetched when first needed and reused until the
+/// Gateway rejects it, at which point a new token is fetched and the request is sent once more.
+#[derive(Debug)]
+pub struct SapSession {
+    profile: Profile,
+    http_client: reqwest::Client,
+    cookies: Arc<Jar>,
+    auth: Authenticator,
+    csrf_url: String,
+    csrf_token: Mutex<Option<String>>,
+}
+
+impl SapSession {
+    pub fn new(profile: &Profile) -> Result<SapSession, String> {
+        let cookies = Arc::new(Jar::default());
+        let mut builder = reqwest::Client::builder().cookie_provider(cookies.clone());
+
+        if let Some(ca_bundle) = &profile.ca_bundle {
+            for cert in read_ca_bundle(ca_bundle).map_err(|err| profile_error(profile, err))? {
+                builder = builder.add_root_certificate(cert);
+            }
+        }
+
+        if let AuthConfig::ClientCertificate(identity) = &profile.auth {
+            builder = builder
+                .identity(read_identity(identity).map_err(|err| profile_error(profile, err))?);
+        }
+
+        let http_client = builder.build().map_err(|err| {
+            profile_error(profile, format!("Unable to create HTTP client: {}", err))
+        })?;
+
+        Ok(SapSession {
+            profile: profile.clone(),
+            auth: Authenticator::new(&profile.auth, http_client.clone()),
+            http_client,
+            cookies,
+            csrf_url: profile.catalog_service_url(),
+            csrf_token: Mutex::new(None),
+        })
+    }
+
+    /// Fetch CSRF tokens from a mock Gateway, which does not use TLS
+    #[cfg(test)]
+    pub(crate) fn with_csrf_url(mut self, csrf_url: String) -> SapSession {
+        self.csrf_url = csrf_url;
+        self
+    }
+
+    pub fn profile(&self) -> &Profile {
+        &self.profile
+    }
+
+    pub async fn get(&self, url: &str) -> Result<Response, anyhow::Error> {
+        log::info!("GET: {}", url);
+        self.send(Method::GET, url, None).await
+    }
+
+    /// Send a POST request with a CSRF token, fetching a new token if the Gateway rejects the current one
+    pub async fn post(&self, url: &str) -> Result<Response, anyhow::Error> {
+        log::info!("POST: {}", url);
+        let mut is_retry = false;
+
+        loop {
+            let token = self.csrf_token().await?;
+            let response = self.send(Method::POST, url, Some(&token)).await?;
+
+            if response.status() != StatusCode::FORBIDDEN || is_retry {
+                return Ok(response);
+            }
+
+            let (rejected, response) = csrf_rejected(response).await?;
+
+            if !rejected {
+                return Ok(response);
+            }
+
+            log::info!(
+                "CSRF token rejected by {}, fetching a new one",
+                self.profile.name
+            );
+            *self.csrf_token.lock().unwrap() = None;
+            is_retry = true;
+        }
+    }
+
+    // - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+    async fn send(
+        &self,
+        method: Method,
+        url: &str,
+        csrf_token: Option<&str>,
+    ) -> Result<Response, anyhow::Error> {
+        let mut logon = !self.has_session_cookie(url);
+
+        loop {
+            let mut request = self.http_client.request(method.clone(), url);
+
+            if logon {
+                if let Some(authorization) = self.auth.authorization().await? {
+                    request = request.header(AUTHORIZATION, authorization);
+                }
+            }
+
+            if let Some(token) = csrf_token {
+                request = request.header(CSRF_HEADER, token);
+            }
+
+            // Next links returned by the Gateway already name the client
+            if let Some(client) = self
+                .profile
+                .client
+                .as_ref()
+                .filter(|_| !has_sap_client(url))
+            {
+                request = request.query(&[(SAP_CLIENT_PARAM, client)]);
+            }
+
+            let response = request.send().await?;
+            log::info!("HTTP Status code = {}", response.status());
+
+            // The Gateway has ended the session, so log on again
+            if response.status() == StatusCode::UNAUTHORIZED && !logon {
+                log::info!("Session with {} has expired", self.profile.name);
+                logon = true;
+                continue;
+            }
+
+            return Ok(response);
+        }
+    }
+
+    fn has_session_cookie(&self, url: &str) -> bool {
+        let Ok(url) = Url::parse(url) else {
+            return false;
+        };
+
+        self.cookies
+            .cookies(&url)
+            .and_then(|header| header.to_str().map(str::to_owned).ok())
+            .is_some_and(|header| {
+                header.split(';').any(|cookie| {
+                    SESSION_COOKIE_PREFIXES
+                        .iter()
+                        .any(|prefix| cookie.trim_start().starts_with(prefix))
+                })
+            })
+    }
+
+    async fn csrf_token(&self) -> Result<String, anyhow::Error> {
+        if let Some(token) = self.csrf_token.lock().unwrap().clone() {
+            return Ok(token);
+        }
+
+        log::info!("Fetching CSRF token from {}", self.csrf_url);
+        let response = self
+            .send(Method::GET, &self.csrf_url, Some(CSRF_FETCH))
+            .await?;
+
+        let token = response
+            .headers()
+            .get(CSRF_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.eq_ignore_ascii_case(CSRF_REQUIRED))
+            .map(str::to_owned)
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} did not return a CSRF token (HTTP status {})",
+                    self.csrf_url,
+                    response.status()
+                )
+            })?;
+
+        *self.csrf_token.lock().unwrap() = Some(token.clone());
+        Ok(token)
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+fn has_sap_client(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| url.query_pairs().any(|(name, _)| name == SAP_CLIENT_PARAM))
+}
+
+/// Check whether a 403 response means the CSRF token was rejected
+///
+/// If the Gateway does not say so in the `X-CSRF-Token` header, the body must be read; the response is then rebuilt so
+/// that the caller can still read it
+async fn csrf_rejected(response: Response) -> Result<(bool, Response), anyhow::Error> {
+    if response.headers().get(CSRF_HEADER).is_some_and(|value| {
+        value
+            .as_bytes()
+            .eq_ignore_ascii_case(CSRF_REQUIRED.as_bytes())
+    }) {
+        return Ok((true, response));
+    }
+
+    let status = response.status();
+    let version = response.version();
+    let headers = response.headers().clone();
+    let body = response.bytes().await?;
+    let rejected = String::from_utf8_lossy(&body).contains(CSRF_FAILURE);
+
+    let mut rebuilt = http::Response::new(body);
+    *rebuilt.status_mut() = status;
+    *rebuilt.version_mut() = version;
+    *rebuilt.headers_mut() = headers;
+
+    Ok((rejected, Response::from(rebuilt)))
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+fn profile_error(profile: &Profile, err: String) -> String {
+    format!("SAP system profile {}: {}", profile.name, err)
+}
+
+fn read_file(path: &Path, what: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|err| format!("Unable to read {} {}: {}", what, path.display(), err))
+}
+
+fn read_ca_bundle(path: &Path) -> Result<Vec<reqwest::Certificate>, String> {
+    let pem = read_file(path, "CA bundle")?;
+    let certs = reqwest::Certificate::from_pem_bundle(&pem)
+        .map_err(|err| format!("Invalid CA bundle {}: {}", path.display(), err))?;
+
+    if certs.is_empty() {
+        Err(format!(
+            "CA bundle {} contains no certificates",
+            path.display()
+        ))
+    } else {
+        Ok(certs)
+    }
+}
+
+fn read_identity(identity: &ClientIdentity) -> Result<reqwest::Identity, String> {
+    match identity {
+        ClientIdentity::Pkcs12 {
+            archive_file,
+            password,
+        } => {
+            let der = read_file(archive_file, "client certificate")?;
+
+            reqwest::Identity::from_pkcs12_der(&der, password.as_deref().unwrap_or_default())
+                .map_err(|err| {
+                    format!(
+                        "Unable to load client certificate {} (is the password correct?): {}",
+                        archive_file.display(),
+                        err
+                    )
+                })
+        }
+
+        ClientIdentity::Pem {
+            cert_file,
+            key_file,
+        } => {
+            let cert = read_file(cert_file, "client certificate")?;
+            let key = read_file(key_file, "client key")?;
+
+            reqwest::Identity::from_pkcs8_pem(&cert, &key).map_err(|err| {
+                format!(
+                    "Unable to load client certificate {} with key {} (the key must be PKCS#8 PEM): {}",
+                    cert_file.display(),
+                    key_file.display(),
+                    err
+                )
+            })
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
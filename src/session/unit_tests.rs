@@ -0,0 +1,274 @@
+use super::*;
+use actix_web::{
+    cookie::Cookie, http::Method as HttpMethod, web, App, HttpRequest, HttpResponse, HttpServer,
+};
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+static SESSION_COOKIE: &str = "SAP_SESSIONID_ES5_002";
+static CATALOG_PATH: &str = "/sap/opu/odata/iwfnd/catalogservice;v=2";
+
+/// The state of the mock Gateway
+#[derive(Default)]
+struct Gateway {
+    logons: AtomicUsize,
+    posts: AtomicUsize,
+    tokens_issued: AtomicUsize,
+    sessions: Mutex<HashSet<String>>,
+    csrf_token: Mutex<Option<String>>,
+    query_strings: Mutex<Vec<String>>,
+}
+
+/// Behave like a Gateway: Basic authentication starts a session, GET requests can fetch a CSRF token, and POST
+/// requests need a valid CSRF token
+async fn gateway(req: HttpRequest, gw: web::Data<Gateway>) -> HttpResponse {
+    gw.query_strings
+        .lock()
+        .unwrap()
+        .push(req.query_string().to_owned());
+
+    let has_session = req
+        .cookie(SESSION_COOKIE)
+        .is_some_and(|cookie| gw.sessions.lock().unwrap().contains(cookie.value()));
+
+    let mut response = if req.headers().contains_key("Authorization") {
+        let session_id = format!("session{}", gw.logons.fetch_add(1, Ordering::SeqCst) + 1);
+        gw.sessions.lock().unwrap().insert(session_id.clone());
+
+        let mut response = HttpResponse::Ok();
+        response.cookie(Cookie::build(SESSION_COOKIE, session_id).path("/").finish());
+        response
+    } else if has_session {
+        HttpResponse::Ok()
+    } else {
+        return HttpResponse::Unauthorized().finish();
+    };
+
+    let csrf_header = req
+        .headers()
+        .get(CSRF_HEADER)
+        .map(|value| value.to_str().unwrap().to_owned());
+
+    if req.method() == HttpMethod::GET {
+        if csrf_header.as_deref() == Some(CSRF_FETCH) {
+            let token = format!(
+                "token{}",
+                gw.tokens_issued.fetch_add(1, Ordering::SeqCst) + 1
+            );
+            *gw.csrf_token.lock().unwrap() = Some(token.clone());
+            response.insert_header((CSRF_HEADER, token));
+        }
+
+        return response.body("OK");
+    }
+
+    gw.posts.fetch_add(1, Ordering::SeqCst);
+
+    if req.path().ends_with("/Forbidden") {
+        HttpResponse::Forbidden().body("No authorization to clear the metadata cache")
+    } else if csrf_header != *gw.csrf_token.lock().unwrap()
+        || req.path().ends_with("/AlwaysRejected")
+    {
+        HttpResponse::Forbidden().body(CSRF_FAILURE)
+    } else {
+        response.body("Cache cleared")
+    }
+}
+
+/// Start a mock Gateway and open a session with it, returning the URL of the catalog service
+fn start_mock_gateway() -> (SapSession, String, Arc<Gateway>) {
+    let gw = Arc::new(Gateway::default());
+    let app_gw = web::Data::from(gw.clone());
+
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(app_gw.clone())
+            .default_service(web::to(gateway))
+    })
+    .workers(1)
+    .bind(("127.0.0.1", 0))
+    .unwrap();
+
+    let addr = server.addrs()[0];
+    actix_rt::spawn(server.run());
+
+    let mut profile = test_profile(
+        AuthConfig::Basic {
+            user: "user".to_owned(),
+            password: "secret".to_owned(),
+        },
+        None,
+    );
+    profile.port = Some(addr.port());
+    profile.client = Some("002".to_owned());
+
+    // The mock Gateway does not use TLS
+    let mut session = SapSession::new(&profile).unwrap();
+    session.csrf_url = format!("http://{}{}/", addr, CATALOG_PATH);
+
+    let catalog_url = session.csrf_url.clone();
+    (session, catalog_url, gw)
+}
+
+fn test_profile(auth: AuthConfig, ca_bundle: Option<&str>) -> Profile {
+    Profile {
+        name: "TEST".to_owned(),
+        hostname: "127.0.0.1".to_owned(),
+        port: None,
+        client: None,
+        auth,
+        ca_bundle: ca_bundle.map(|f| PathBuf::from(format!("./test_data/tls/{}", f))),
+        catalog_path: CATALOG_PATH.to_owned(),
+        format: Default::default(),
+        allow_cache_clear: false,
+    }
+}
+
+fn pem_identity(key_file: &str) -> AuthConfig {
+    AuthConfig::ClientCertificate(ClientIdentity::Pem {
+        cert_file: PathBuf::from("./test_data/tls/client.crt"),
+        key_file: PathBuf::from(format!("./test_data/tls/{}", key_file)),
+    })
+}
+
+fn pkcs12_identity(password: &str) -> AuthConfig {
+    AuthConfig::ClientCertificate(ClientIdentity::Pkcs12 {
+        archive_file: PathBuf::from("./test_data/tls/client.p12"),
+        password: Some(password.to_owned()),
+    })
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[actix_web::test]
+pub async fn should_reuse_logon_session() {
+    let (session, catalog_url, gw) = start_mock_gateway();
+
+    for _ in 0..3 {
+        let response = session
+            .get(&format!("{}CatalogCollection", catalog_url))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    assert_eq!(gw.logons.load(Ordering::SeqCst), 1);
+    assert!(gw
+        .query_strings
+        .lock()
+        .unwrap()
+        .iter()
+        .all(|qs| qs == "sap-client=002"));
+}
+
+#[actix_web::test]
+pub async fn should_log_on_again_when_session_expires() {
+    let (session, catalog_url, gw) = start_mock_gateway();
+
+    session.get(&catalog_url).await.unwrap();
+    gw.sessions.lock().unwrap().clear();
+
+    let response = session.get(&catalog_url).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(gw.logons.load(Ordering::SeqCst), 2);
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[actix_web::test]
+pub async fn should_fetch_csrf_token_on_demand() {
+    let (session, catalog_url, gw) = start_mock_gateway();
+    let url = format!("{}ClearMetadataCacheForService", catalog_url);
+
+    session.get(&catalog_url).await.unwrap();
+    assert_eq!(gw.tokens_issued.load(Ordering::SeqCst), 0);
+
+    for _ in 0..2 {
+        let response = session.post(&url).await.unwrap();
+        assert_eq!(response.text().await.unwrap(), "Cache cleared");
+    }
+
+    assert_eq!(gw.tokens_issued.load(Ordering::SeqCst), 1);
+    assert_eq!(gw.logons.load(Ordering::SeqCst), 1);
+}
+
+#[actix_web::test]
+pub async fn should_retry_once_when_csrf_token_is_rejected() {
+    let (session, catalog_url, gw) = start_mock_gateway();
+
+    // The Gateway has forgotten the token issued to this session
+    *session.csrf_token.lock().unwrap() = Some("stale".to_owned());
+    session.get(&catalog_url).await.unwrap();
+
+    let response = session
+        .post(&format!("{}ClearMetadataCacheForService", catalog_url))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(gw.posts.load(Ordering::SeqCst), 2);
+    assert_eq!(gw.tokens_issued.load(Ordering::SeqCst), 1);
+
+    let response = session
+        .post(&format!("{}AlwaysRejected", catalog_url))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(gw.posts.load(Ordering::SeqCst), 4);
+}
+
+#[actix_web::test]
+pub async fn should_return_other_forbidden_responses_intact() {
+    let (session, catalog_url, gw) = start_mock_gateway();
+
+    let response = session
+        .post(&format!("{}Forbidden", catalog_url))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert_eq!(
+        response.text().await.unwrap(),
+        "No authorization to clear the metadata cache"
+    );
+    assert_eq!(gw.posts.load(Ordering::SeqCst), 1);
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[actix_web::test]
+pub async fn should_load_client_certificate() {
+    // The certificate alone identifies the user
+    let session =
+        SapSession::new(&test_profile(pem_identity("client.key"), Some("ca.crt"))).unwrap();
+    assert_eq!(session.auth.authorization().await.unwrap(), None);
+
+    let session =
+        SapSession::new(&test_profile(pkcs12_identity("changeit"), Some("ca.crt"))).unwrap();
+    assert_eq!(session.auth.authorization().await.unwrap(), None);
+}
+
+#[test]
+pub fn should_report_unusable_client_certificate() {
+    let err = SapSession::new(&test_profile(pem_identity("missing.key"), None)).unwrap_err();
+    assert!(
+        err.starts_with(
+            "SAP system profile TEST: Unable to read client key ./test_data/tls/missing.key"
+        ),
+        "{}",
+        err
+    );
+
+    let err = SapSession::new(&test_profile(pkcs12_identity("wrong"), None)).unwrap_err();
+    assert!(err.contains("is the password correct?"), "{}", err);
+
+    // A private key file contains no certificates
+    let err = SapSession::new(&test_profile(
+        pem_identity("client.key"),
+        Some("client.key"),
+    ))
+    .unwrap_err();
+    assert!(
+        err.contains("CA bundle ./test_data/tls/client.key"),
+        "{}",
+        err
+    );
+}
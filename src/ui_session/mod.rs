@@ -0,0 +1,291 @@
+use actix_web::{
+    cookie::{Cookie, SameSite},
+    HttpRequest, HttpResponse,
+};
+use rand::{distributions::Alphanumeric, Rng};
+use read_sap_odata_catalog::{
+    catalogservice::{Annotation, Service, Tag, Vocabulary},
+    project::SaveSummary,
+    ExpandedService, ProbeResult, ServiceSearch, VocabularyStore,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+static COOKIE_NAME: &str = "catalog_session";
+static SESSION_ID_LENGTH: usize = 32;
+
+/// Sessions that have not been used for this long are discarded
+static IDLE_TIMEOUT: Duration = Duration::from_secs(8 * 60 * 60);
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// What one browser is currently looking at
+#[derive(Debug, Default, Clone)]
+pub struct UiState {
+    pub profile: Option<String>,
+    pub catalog_list: Option<Vec<String>>,
+    pub service_list: Option<Vec<(String, String)>>,
+    /// The catalog the listed services were read from, if they were not found some other way
+    pub service_catalog: Option<String>,
+    pub service_search: ServiceSearch,
+    pub service_count: Option<String>,
+    pub service_details: Option<Vec<ServiceDetails>>,
+    pub best_match_form: BestMatchForm,
+    pub best_match: Option<BestMatch>,
+    pub annotation_list: Option<Vec<Annotation>>,
+    pub annotation_usage: Option<AnnotationUsage>,
+    pub vocabulary_list: Option<Vec<VocabularyRow>>,
+    pub tag_list: Option<Vec<TagRow>>,
+    pub probe_results: Option<Vec<ProbeResult>>,
+    /// The service whose metadata cache is to be cleared once the user confirms
+    pub pending_cache_clear: Option<String>,
+    /// Shown once, then discarded
+    pub info_msg: Option<String>,
+    /// The metadata read after clearing a service's cache.  Shown once, then discarded.
+    pub refreshed_metadata: Option<String>,
+    /// The service whose metadata was saved in the project directory, and what saving it did.  Shown once.
+    pub metadata_save: Option<(String, SaveSummary)>,
+    pub error_msg: Option<String>,
+    pub last_srv: Option<String>,
+}
+
+/// A service listed with the names of its entity sets, tags and annotation files
+#[derive(Debug, Clone, Serialize)]
+pub struct ServiceDetails {
+    pub id: String,
+    pub metadata_url: String,
+    pub entity_sets: String,
+    pub tags: String,
+    pub annotations: String,
+}
+
+impl From<&ExpandedService> for ServiceDetails {
+    fn from(expanded: &ExpandedService) -> Self {
+        fn names<T>(list: &Option<Vec<T>>, name: fn(&T) -> String) -> String {
+            list.iter()
+                .flatten()
+                .map(name)
+                .collect::<Vec<String>>()
+                .join(", ")
+        }
+
+        ServiceDetails {
+            id: expanded.service.id.clone(),
+            metadata_url: expanded.service.metadata_url.clone(),
+            entity_sets: names(&expanded.entity_sets, |es| es.id.clone()),
+            tags: names(&expanded.tags, |tag| tag.text.clone()),
+            annotations: names(&expanded.annotations, |anno| {
+                format!("{} ({})", anno.technical_name, anno.version)
+            }),
+        }
+    }
+}
+
+/// The parameters of the `BestMatchingService` function import, as entered
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BestMatchForm {
+    pub technical_service_name: String,
+    pub version_min: String,
+    pub version_max: String,
+}
+
+impl BestMatchForm {
+    /// The technical service name, as entered but trimmed, and version range.  A blank version leaves that end of the
+    /// range open.
+    pub fn parameters(&self) -> Result<(String, i16, i16), String> {
+        let name = self.technical_service_name.trim();
+        if name.is_empty() {
+            return Err("Enter a technical service name".to_owned());
+        }
+
+        let version = |text: &str, default: i16| match text.trim() {
+            "" => Ok(default),
+            version => version
+                .parse::<i16>()
+                .ok()
+                .filter(|v| *v > 0)
+                .ok_or_else(|| format!("Invalid technical service version '{}'", text)),
+        };
+
+        let version_min = version(&self.version_min, 1)?;
+        let version_max = version(&self.version_max, i16::MAX)?;
+
+        if version_min > version_max {
+            return Err(format!(
+                "The minimum version {} is greater than the maximum version {}",
+                version_min, version_max
+            ));
+        }
+
+        Ok((name.to_owned(), version_min, version_max))
+    }
+}
+
+/// The service returned by the `BestMatchingService` function import
+#[derive(Debug, Clone, Serialize)]
+pub struct BestMatch {
+    pub id: String,
+    pub title: String,
+    pub description: String,
+    pub technical_service_version: i16,
+    pub service_url: String,
+    pub metadata_url: String,
+}
+
+impl From<Service> for BestMatch {
+    fn from(service: Service) -> Self {
+        BestMatch {
+            id: service.id,
+            title: service.title,
+            description: service.description,
+            technical_service_version: service.technical_service_version,
+            service_url: service.service_url,
+            metadata_url: service.metadata_url,
+        }
+    }
+}
+
+/// The services that use one version of an annotation file, as (ID, metadata URL) pairs
+#[derive(Debug, Clone, Serialize)]
+pub struct AnnotationUsage {
+    pub technical_name: String,
+    pub version: String,
+    pub services: Vec<(String, String)>,
+}
+
+/// A tag and the number of services that carry it
+#[derive(Debug, Clone, Serialize)]
+pub struct TagRow {
+    pub id: String,
+    pub text: String,
+    pub occurrence: i16,
+}
+
+impl TagRow {
+    /// The most frequently used tags come first; tags used equally often are listed alphabetically
+    pub fn ranked(tags: Vec<Tag>) -> Vec<TagRow> {
+        let mut rows: Vec<TagRow> = tags
+            .into_iter()
+            .map(|tag| TagRow {
+                id: tag.id,
+                text: tag.text,
+                occurrence: tag.occurrence,
+            })
+            .collect();
+
+        rows.sort_by(|a, b| {
+            b.occurrence
+                .cmp(&a.occurrence)
+                .then_with(|| a.text.cmp(&b.text))
+        });
+        rows
+    }
+}
+
+/// A vocabulary known to the Gateway, and where it is stored locally (if it has been)
+#[derive(Debug, Clone, Serialize)]
+pub struct VocabularyRow {
+    pub technical_name: String,
+    pub version: String,
+    pub sap_origin: String,
+    pub namespace: String,
+    pub description: String,
+    pub local_file: Option<String>,
+}
+
+impl VocabularyRow {
+    pub fn new(vocabulary: &Vocabulary, store: &VocabularyStore) -> VocabularyRow {
+        VocabularyRow {
+            technical_name: vocabulary.technical_name.clone(),
+            version: vocabulary.version.clone(),
+            sap_origin: vocabulary.sap_origin.clone(),
+            namespace: vocabulary.namespace.clone(),
+            description: vocabulary.description.clone(),
+            local_file: store
+                .is_stored(vocabulary)
+                .then(|| store.path(vocabulary).display().to_string()),
+        }
+    }
+}
+
+/// The UI state of the browser that sent the current request
+#[derive(Debug)]
+pub struct UiSession {
+    id: String,
+    is_new: bool,
+    pub state: UiState,
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Server-side store of UI state, keyed by a random session id held in a cookie
+///
+/// The state stays on the server because a large catalog's service list would not fit in a cookie
+#[derive(Debug, Default)]
+pub struct UiSessionStore {
+    sessions: Mutex<HashMap<String, (Instant, UiState)>>,
+}
+
+impl UiSessionStore {
+    /// Load the state of the browser that sent the request, starting a new session if its cookie is missing or expired
+    pub fn load(&self, req: &HttpRequest) -> UiSession {
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap();
+
+        if let Some(cookie) = req.cookie(COOKIE_NAME) {
+            if let Some((_, state)) = sessions
+                .get(cookie.value())
+                .filter(|(last_used, _)| now.duration_since(*last_used) < IDLE_TIMEOUT)
+            {
+                return UiSession {
+                    id: cookie.value().to_owned(),
+                    is_new: false,
+                    state: state.clone(),
+                };
+            }
+        }
+
+        sessions.retain(|_, (last_used, _)| now.duration_since(*last_used) < IDLE_TIMEOUT);
+
+        UiSession {
+            id: new_session_id(),
+            is_new: true,
+            state: UiState::default(),
+        }
+    }
+
+    /// Store the session's state and, for a new session, add the session cookie to the response
+    pub fn save(&self, session: UiSession, response: &mut HttpResponse) {
+        if session.is_new {
+            let cookie = Cookie::build(COOKIE_NAME, &session.id)
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .finish();
+
+            if let Err(err) = response.add_cookie(&cookie) {
+                log::error!("Unable to set session cookie: {}", err);
+            }
+        }
+
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(session.id, (Instant::now(), session.state));
+    }
+}
+
+fn new_session_id() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SESSION_ID_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
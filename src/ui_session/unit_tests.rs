@@ -0,0 +1,125 @@
+use super::*;
+use actix_web::test::TestRequest;
+
+fn session_cookie(response: &HttpResponse) -> Option<Cookie<'static>> {
+    response
+        .cookies()
+        .find(|cookie| cookie.name() == COOKIE_NAME)
+        .map(|cookie| cookie.into_owned())
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_start_new_session_with_cookie() {
+    let store = UiSessionStore::default();
+    let ui = store.load(&TestRequest::default().to_http_request());
+    assert!(ui.is_new);
+    assert!(ui.state.catalog_list.is_none());
+
+    let mut response = HttpResponse::Ok().finish();
+    store.save(ui, &mut response);
+
+    let cookie = session_cookie(&response).unwrap();
+    assert_eq!(cookie.value().len(), SESSION_ID_LENGTH);
+    assert_eq!(cookie.http_only(), Some(true));
+
+    // A known session does not need its cookie to be set again
+    let ui = store.load(&TestRequest::default().cookie(cookie).to_http_request());
+    assert!(!ui.is_new);
+
+    let mut response = HttpResponse::Ok().finish();
+    store.save(ui, &mut response);
+    assert!(session_cookie(&response).is_none());
+}
+
+#[test]
+pub fn should_keep_browser_sessions_apart() {
+    let store = UiSessionStore::default();
+    let mut cookies = Vec::new();
+
+    for catalog in ["ES5", "LOCAL"] {
+        let mut ui = store.load(&TestRequest::default().to_http_request());
+        ui.state.catalog_list = Some(vec![catalog.to_owned()]);
+        ui.state.error_msg = Some(format!("Error in {}", catalog));
+
+        let mut response = HttpResponse::Ok().finish();
+        store.save(ui, &mut response);
+        cookies.push(session_cookie(&response).unwrap());
+    }
+
+    assert_ne!(cookies[0].value(), cookies[1].value());
+
+    let first = store.load(
+        &TestRequest::default()
+            .cookie(cookies[0].clone())
+            .to_http_request(),
+    );
+    assert_eq!(first.state.catalog_list, Some(vec!["ES5".to_owned()]));
+    assert_eq!(first.state.error_msg.as_deref(), Some("Error in ES5"));
+
+    let second = store.load(
+        &TestRequest::default()
+            .cookie(cookies[1].clone())
+            .to_http_request(),
+    );
+    assert_eq!(second.state.catalog_list, Some(vec!["LOCAL".to_owned()]));
+}
+
+#[test]
+pub fn should_ignore_unknown_session_cookie() {
+    let store = UiSessionStore::default();
+    let req = TestRequest::default()
+        .cookie(Cookie::new(COOKIE_NAME, "forged"))
+        .to_http_request();
+    let ui = store.load(&req);
+
+    assert!(ui.is_new);
+    assert_ne!(ui.id, "forged");
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_validate_best_match_parameters() {
+    let form = |name: &str, min: &str, max: &str| BestMatchForm {
+        technical_service_name: name.to_owned(),
+        version_min: min.to_owned(),
+        version_max: max.to_owned(),
+    };
+
+    assert_eq!(
+        form(" /abc/Zpdcds_Srv ", "", "").parameters(),
+        Ok(("/abc/Zpdcds_Srv".to_owned(), 1, i16::MAX))
+    );
+    assert_eq!(
+        form("ZPDCDS_SRV", "2", "3").parameters(),
+        Ok(("ZPDCDS_SRV".to_owned(), 2, 3))
+    );
+    assert_eq!(
+        form("", "1", "1").parameters(),
+        Err("Enter a technical service name".to_owned())
+    );
+    assert_eq!(
+        form("ZPDCDS_SRV", "0", "").parameters(),
+        Err("Invalid technical service version '0'".to_owned())
+    );
+    assert!(form("ZPDCDS_SRV", "3", "2").parameters().is_err());
+}
+
+#[test]
+pub fn should_rank_tags_by_occurrence() {
+    let tag = |text: &str, occurrence: i16| Tag {
+        id: text.to_uppercase(),
+        text: text.to_owned(),
+        occurrence,
+    };
+
+    let ranked = TagRow::ranked(vec![
+        tag("Sales", 2),
+        tag("Finance", 7),
+        tag("Purchasing", 2),
+    ]);
+    let texts: Vec<&str> = ranked.iter().map(|row| row.text.as_str()).collect();
+
+    assert_eq!(texts, ["Finance", "Purchasing", "Sales"]);
+    assert_eq!(ranked[0].id, "FINANCE");
+}
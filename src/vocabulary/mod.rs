@@ -0,0 +1,103 @@
+use crate::catalogservice::Vocabulary;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Local copies of the vocabulary files read from the Gateway, so that the terms used in annotation files can be
+/// resolved offline
+///
+/// Annotation files include a vocabulary by its namespace (for example, `<edmx:Include
+/// Namespace="com.sap.vocabularies.UI.v1" Alias="UI"/>`), so each vocabulary is stored in a file named after its
+/// namespace.
+#[derive(Debug, Clone)]
+pub struct VocabularyStore {
+    dir: PathBuf,
+}
+
+impl VocabularyStore {
+    pub fn new(dir: impl Into<PathBuf>) -> VocabularyStore {
+        VocabularyStore { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The file in which a vocabulary is stored.  A vocabulary without a namespace is named after its technical name
+    /// and version instead.
+    pub fn path(&self, vocabulary: &Vocabulary) -> PathBuf {
+        let file_name = if vocabulary.namespace.trim().is_empty() {
+            format!(
+                "{}_{}.xml",
+                safe_file_name(&vocabulary.technical_name),
+                safe_file_name(&vocabulary.version)
+            )
+        } else {
+            format!("{}.xml", safe_file_name(&vocabulary.namespace))
+        };
+
+        self.dir.join(file_name)
+    }
+
+    pub fn is_stored(&self, vocabulary: &Vocabulary) -> bool {
+        self.path(vocabulary).is_file()
+    }
+
+    /// Write a vocabulary file, replacing any earlier copy, and return its path
+    pub fn save(&self, vocabulary: &Vocabulary, raw_xml: &str) -> Result<PathBuf, String> {
+        fs::create_dir_all(&self.dir).map_err(|err| {
+            format!(
+                "Unable to create vocabulary directory {}: {}",
+                self.dir.display(),
+                err
+            )
+        })?;
+
+        let path = self.path(vocabulary);
+        fs::write(&path, raw_xml).map_err(|err| {
+            format!(
+                "Unable to write vocabulary file {}: {}",
+                path.display(),
+                err
+            )
+        })?;
+
+        Ok(path)
+    }
+
+    /// The stored vocabulary file for a namespace
+    pub fn load(&self, namespace: &str) -> Result<String, String> {
+        let path = self.dir.join(format!("{}.xml", safe_file_name(namespace)));
+
+        fs::read_to_string(&path).map_err(|err| {
+            format!(
+                "Vocabulary {} has not been stored in {}: {}",
+                namespace,
+                self.dir.display(),
+                err
+            )
+        })
+    }
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+/// Make a technical name or namespace usable as a file name.  Technical names such as `/IWBEP/VOC_UI` contain slashes,
+/// so anything other than a letter, digit, `.`, `-` or `_` becomes `_`.
+pub fn safe_file_name(name: &str) -> String {
+    name.trim_matches('/')
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------------------------------------------------
+#[cfg(test)]
+pub mod unit_tests;
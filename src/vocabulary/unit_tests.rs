@@ -0,0 +1,53 @@
+use super::*;
+
+fn vocabulary(technical_name: &str, namespace: &str) -> Vocabulary {
+    Vocabulary {
+        technical_name: technical_name.to_owned(),
+        version: "0001".to_owned(),
+        sap_origin: "LOCAL".to_owned(),
+        namespace: namespace.to_owned(),
+        ..Default::default()
+    }
+}
+
+// - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -
+#[test]
+pub fn should_name_files_after_namespace() {
+    let store = VocabularyStore::new("vocabularies");
+
+    assert_eq!(
+        store.path(&vocabulary("/IWBEP/VOC_UI", "com.sap.vocabularies.UI.v1")),
+        Path::new("vocabularies/com.sap.vocabularies.UI.v1.xml")
+    );
+    assert_eq!(
+        store.path(&vocabulary("/IWBEP/VOC_UI", "")),
+        Path::new("vocabularies/IWBEP_VOC_UI_0001.xml")
+    );
+
+    let mut traversal = vocabulary("/IWBEP/VOC_UI", "");
+    traversal.version = "0001/../../etc/passwd".to_owned();
+    assert_eq!(
+        store.path(&traversal),
+        Path::new("vocabularies/IWBEP_VOC_UI_0001_.._.._etc_passwd.xml")
+    );
+}
+
+#[test]
+pub fn should_save_and_load_vocabulary() {
+    let dir = std::env::temp_dir().join(format!("vocabularies-{}", std::process::id()));
+    let store = VocabularyStore::new(&dir);
+    let ui = vocabulary("/IWBEP/VOC_UI", "com.sap.vocabularies.UI.v1");
+
+    assert!(!store.is_stored(&ui));
+    store.save(&ui, "<edmx:Edmx/>").unwrap();
+    assert!(store.is_stored(&ui));
+    assert_eq!(
+        store.load("com.sap.vocabularies.UI.v1").unwrap(),
+        "<edmx:Edmx/>"
+    );
+
+    let err = store.load("com.sap.vocabularies.Common.v1").unwrap_err();
+    assert!(err.contains("has not been stored"), "{}", err);
+
+    fs::remove_dir_all(dir).unwrap();
+}